@@ -0,0 +1,213 @@
+//! Embedded ADMS ("iclock") push server. Terminals in cloud/push mode open HTTP
+//! connections to us, upload their ATTLOG/OPERLOG tables and poll for queued
+//! commands, so sites behind NAT that cannot be polled still deliver punches.
+//! Only serials registered for push in the device settings are served; anyone
+//! can reach the port, and unknown terminals must not pile up in memory.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::Local;
+use log::{debug, info, warn};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zkteco_client::AttendanceRecord;
+
+mod http;
+mod protocol;
+
+use http::*;
+use protocol::*;
+
+pub const ADMS_DEFAULT_PORT: u16 = 8081;
+
+// Largest request body accepted; a full ATTLOG re-upload of a busy terminal fits easily
+const MAX_BODY: usize = 16 * 1024 * 1024;
+const MAX_HEADER_LINE: usize = 8 * 1024;
+// Keep-alive connections idle this long are dropped (terminals poll every few seconds)
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A push-mode terminal that has contacted the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmsDeviceStatus {
+    pub serial: String,
+    pub ip: String,
+    pub push_version: Option<String>,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub records_received: u64,
+    pub users_known: usize,         // Names learned from OPERLOG uploads
+    pub pending_commands: usize,
+}
+
+/// A command waiting for, or already sent to, a terminal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmsCommand {
+    pub id: u32,
+    pub command: String,            // e.g. "REBOOT", "INFO", "DATA QUERY ATTLOG StartTime=...\tEndTime=..."
+    pub queued_at: String,
+    pub sent_at: Option<String>,
+}
+
+/// A terminal's answer to a queued command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmsCommandResult {
+    pub serial: String,
+    pub id: u32,
+    pub return_code: i32,           // 0 = success
+    pub command: String,
+    pub received_at: String,
+}
+
+/// Punches uploaded by one terminal in one request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmsPunchBatch {
+    pub device_serial: String,
+    pub ip: String,
+    pub records: Vec<AttendanceRecord>,
+}
+
+pub enum AdmsEvent {
+    Device(AdmsDeviceStatus),
+    Punches(AdmsPunchBatch),
+    CommandResult(AdmsCommandResult),
+}
+
+#[derive(Default)]
+struct DeviceState {
+    ip: String,
+    push_version: Option<String>,
+    first_seen: String,
+    last_seen: String,
+    records_received: u64,
+    users: HashMap<String, String>,  // PIN -> name
+    stamps: HashMap<String, String>, // Table -> last upload Stamp, echoed in the handshake
+    queue: VecDeque<AdmsCommand>,
+    sent: Vec<AdmsCommand>,
+}
+
+impl DeviceState {
+    fn status(&self, serial: &str) -> AdmsDeviceStatus {
+        AdmsDeviceStatus {
+            serial: serial.to_string(),
+            ip: self.ip.clone(),
+            push_version: self.push_version.clone(),
+            first_seen: self.first_seen.clone(),
+            last_seen: self.last_seen.clone(),
+            records_received: self.records_received,
+            users_known: self.users.len(),
+            pending_commands: self.queue.len(),
+        }
+    }
+}
+
+struct Shared {
+    devices: Mutex<HashMap<String, DeviceState>>,
+    next_command_id: AtomicU32,
+    settings: Arc<DeviceSettingsStore>,
+    on_event: Box<dyn Fn(AdmsEvent) + Send + Sync>,
+}
+
+pub struct AdmsServer {
+    port: u16,
+    shared: Arc<Shared>,
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl AdmsServer {
+    /// Listen on `bind` (port 0 picks a free one) and serve terminals until stopped
+    pub async fn start<F>(bind: SocketAddr, settings: Arc<DeviceSettingsStore>, on_event: F) -> Result<Self, String>
+    where
+        F: Fn(AdmsEvent) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind(bind).await
+            .map_err(|e| format!("Cannot listen on {}: {}", bind, e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let shared = Arc::new(Shared {
+            devices: Mutex::new(HashMap::new()),
+            next_command_id: AtomicU32::new(1),
+            settings,
+            on_event: Box::new(on_event),
+        });
+        let (stop, stopped) = watch::channel(false);
+
+        let accept_shared = Arc::clone(&shared);
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!("ADMS accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let shared = Arc::clone(&accept_shared);
+                let stopped = stopped.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(stream, peer, shared, stopped).await {
+                        debug!("ADMS connection from {} closed: {}", peer, e);
+                    }
+                });
+            }
+        });
+
+        info!("☁️ ADMS push server listening on port {}", port);
+        Ok(AdmsServer { port, shared, stop, task })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn devices(&self) -> Vec<AdmsDeviceStatus> {
+        let devices = match self.shared.devices.lock() {
+            Ok(devices) => devices,
+            Err(_) => return Vec::new(),
+        };
+        let mut list: Vec<_> = devices.iter().map(|(serial, d)| d.status(serial)).collect();
+        list.sort_by(|a, b| a.serial.cmp(&b.serial));
+        list
+    }
+
+    /// Queue a raw iclock command for a terminal; it is sent on the terminal's next poll
+    pub fn queue_command(&self, serial: &str, command: &str) -> Result<u32, String> {
+        let command = command.trim();
+        if command.is_empty() || command.contains('\n') {
+            return Err("Command must be a single non-empty line".to_string());
+        }
+        let mut devices = self.shared.devices.lock().map_err(|e| format!("Lock poisoned: {}", e))?;
+        let device = devices.get_mut(serial)
+            .ok_or_else(|| format!("Terminal {} has not contacted the server", serial))?;
+        let id = self.shared.next_command_id.fetch_add(1, Ordering::Relaxed);
+        device.queue.push_back(AdmsCommand {
+            id,
+            command: command.to_string(),
+            queued_at: Local::now().to_rfc3339(),
+            sent_at: None,
+        });
+        info!("☁️ Queued command {} for {}: {}", id, serial, command);
+        Ok(id)
+    }
+
+    pub fn stop(&self) {
+        let _ = self.stop.send(true);
+        self.task.abort();
+        info!("☁️ ADMS push server on port {} stopped", self.port);
+    }
+}
+
+impl Drop for AdmsServer {
+    fn drop(&mut self) {
+        let _ = self.stop.send(true);
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,157 @@
+//! Minimal HTTP/1.1 for the iclock endpoints: request reading and connection handling
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use chrono::Utc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use super::*;
+
+pub(super) struct Request {
+    pub(super) method: String,
+    pub(super) path: String,
+    pub(super) query: HashMap<String, String>,
+    pub(super) body: Vec<u8>,
+    pub(super) keep_alive: bool,
+}
+
+pub(super) async fn serve_connection(
+    stream: TcpStream,
+    peer: SocketAddr,
+    shared: Arc<Shared>,
+    mut stopped: watch::Receiver<bool>,
+) -> Result<(), String> {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let request = tokio::select! {
+            r = tokio::time::timeout(IDLE_TIMEOUT, read_request(&mut reader)) => match r {
+                Ok(r) => r?,
+                Err(_) => return Ok(()),
+            },
+            _ = stopped.changed() => return Ok(()),
+        };
+        let request = match request {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+
+        let (status, body, events) = handle(&shared, &peer.ip().to_string(), &request);
+        for event in events {
+            (shared.on_event)(event);
+        }
+
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nDate: {}\r\nConnection: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            Utc::now().format("%a, %d %b %Y %H:%M:%S GMT"),
+            if request.keep_alive { "keep-alive" } else { "close" },
+            body,
+        );
+        reader.get_mut().write_all(response.as_bytes()).await.map_err(|e| e.to_string())?;
+        if !request.keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+/// Read one HTTP/1.x request; None when the peer closed the connection
+async fn read_request(reader: &mut BufReader<TcpStream>) -> Result<Option<Request>, String> {
+    let line = match read_line(reader).await? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or("Empty request line")?.to_ascii_uppercase();
+    let target = parts.next().ok_or("Request line has no path")?.to_string();
+    let mut keep_alive = parts.next() != Some("HTTP/1.0");
+
+    let mut content_length = 0usize;
+    loop {
+        let header = read_line(reader).await?.ok_or("Connection closed inside headers")?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.parse().map_err(|_| "Bad Content-Length")?,
+                "connection" => keep_alive = !value.eq_ignore_ascii_case("close"),
+                _ => {}
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(format!("Body of {} bytes is too large", content_length));
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await.map_err(|e| e.to_string())?;
+
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    Ok(Some(Request {
+        method,
+        path: path.trim_end_matches(".aspx").to_ascii_lowercase(),
+        query: parse_pairs(query, '&'),
+        body,
+        keep_alive,
+    }))
+}
+
+/// Read one line, giving up once it exceeds MAX_HEADER_LINE rather than buffering
+/// whatever the peer sends; None at end of stream
+async fn read_line(reader: &mut BufReader<TcpStream>) -> Result<Option<String>, String> {
+    let mut line = Vec::new();
+    loop {
+        let available = reader.fill_buf().await.map_err(|e| e.to_string())?;
+        if available.is_empty() {
+            return Ok((!line.is_empty()).then(|| String::from_utf8_lossy(&line).into_owned()));
+        }
+        let (chunk, complete) = match available.iter().position(|&b| b == b'\n') {
+            Some(end) => (&available[..=end], true),
+            None => (available, false),
+        };
+        if line.len() + chunk.len() > MAX_HEADER_LINE {
+            return Err("Header line too long".to_string());
+        }
+        line.extend_from_slice(chunk);
+        let used = chunk.len();
+        reader.consume(used);
+        if complete {
+            return Ok(Some(String::from_utf8_lossy(&line).into_owned()));
+        }
+    }
+}
+
+/// Parse `a=1&b=2` style pairs (also the tab separated `key=value` lists of OPERLOG)
+pub(super) fn parse_pairs(s: &str, separator: char) -> HashMap<String, String> {
+    s.split(separator)
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (url_decode(k.trim()), url_decode(v.trim())))
+        .collect()
+}
+
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
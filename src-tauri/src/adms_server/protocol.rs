@@ -0,0 +1,212 @@
+//! The iclock protocol: routing, the handshake and ATTLOG parsing
+
+use std::collections::HashMap;
+use chrono::{Local, NaiveDateTime, Offset, Utc};
+use chrono_tz::Tz;
+use log::{debug, info, warn};
+use crate::zkteco_client::{localize, AttendanceRecord, PunchState, VerifyMethod};
+use super::*;
+
+/// Route one request; returns the HTTP status line, the body, and events to publish
+pub(super) fn handle(shared: &Shared, ip: &str, request: &Request) -> (&'static str, String, Vec<AdmsEvent>) {
+    let serial = match request.query.get("SN").filter(|sn| !sn.is_empty()) {
+        Some(serial) => serial.clone(),
+        None if request.path == "/iclock/ping" => return ("200 OK", "OK".to_string(), Vec::new()),
+        None => return ("400 Bad Request", "Missing SN".to_string(), Vec::new()),
+    };
+    if !shared.settings.get(&serial).push_allowed {
+        debug!("☁️ Refused unregistered terminal {} from {}", serial, ip);
+        return ("401 Unauthorized", "Unknown device".to_string(), Vec::new());
+    }
+    let mut devices = match shared.devices.lock() {
+        Ok(devices) => devices,
+        Err(_) => return ("500 Internal Server Error", "Server error".to_string(), Vec::new()),
+    };
+
+    let now = Local::now().to_rfc3339();
+    let is_new = !devices.contains_key(&serial);
+    let device = devices.entry(serial.clone()).or_default();
+    if is_new {
+        device.first_seen = now.clone();
+        info!("☁️ Terminal {} connected from {}", serial, ip);
+    }
+    device.ip = ip.to_string();
+    device.last_seen = now.clone();
+    if let Some(version) = request.query.get("pushver") {
+        device.push_version = Some(version.clone());
+    }
+    let mut events = Vec::new();
+
+    let body = String::from_utf8_lossy(&request.body);
+    let reply = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/iclock/cdata") => {
+            events.push(AdmsEvent::Device(device.status(&serial)));
+            handshake(&serial, shared.settings.get(&serial).timezone(), &device.stamps)
+        }
+        ("POST", "/iclock/cdata") => {
+            let table = request.query.get("table").map(|t| t.to_ascii_uppercase()).unwrap_or_default();
+            let reply = match table.as_str() {
+                "ATTLOG" => {
+                    let zone = shared.settings.get(&serial).timezone();
+                    let lines: Vec<&str> = body.lines().filter(|l| !l.trim().is_empty()).collect();
+                    let records: Vec<AttendanceRecord> = lines.iter()
+                        .filter_map(|l| parse_attlog_line(l, &device.users, zone))
+                        .collect();
+                    let count = records.len();
+                    // Refuse the whole upload so the terminal keeps the rows and sends them again
+                    if count < lines.len() {
+                        warn!("☁️ {} pushed {} ATTLOG line(s) without a numeric PIN, upload refused", serial, lines.len() - count);
+                        return ("400 Bad Request", format!("{} line(s) without a numeric PIN", lines.len() - count), events);
+                    }
+                    device.records_received += count as u64;
+                    info!("☁️ {} pushed {} punches", serial, count);
+                    if count > 0 {
+                        events.push(AdmsEvent::Punches(AdmsPunchBatch {
+                            device_serial: serial.clone(),
+                            ip: ip.to_string(),
+                            records,
+                        }));
+                    }
+                    format!("OK: {}", count)
+                }
+                "OPERLOG" | "USERINFO" => {
+                    let mut count = 0;
+                    for line in body.lines().filter(|l| !l.trim().is_empty()) {
+                        count += 1;
+                        if let Some(user) = line.strip_prefix("USER ") {
+                            let fields = parse_pairs(user, '\t');
+                            if let Some(pin) = fields.get("PIN") {
+                                let name = fields.get("Name").cloned().unwrap_or_default();
+                                device.users.insert(pin.clone(), name);
+                            }
+                        }
+                    }
+                    debug!("☁️ {} pushed {} {} lines", serial, count, table);
+                    format!("OK: {}", count)
+                }
+                // Photos and other tables are acknowledged so the terminal moves on
+                _ => format!("OK: {}", body.lines().count()),
+            };
+            // The terminal only re-sends rows newer than the stamp it gets back
+            if let Some(stamp) = request.query.get("Stamp").filter(|s| !s.is_empty()) {
+                device.stamps.insert(table, stamp.clone());
+            }
+            reply
+        }
+        ("GET", "/iclock/getrequest") => {
+            if device.queue.is_empty() {
+                "OK".to_string()
+            } else {
+                let mut lines = Vec::new();
+                while let Some(mut command) = device.queue.pop_front() {
+                    lines.push(format!("C:{}:{}", command.id, command.command));
+                    command.sent_at = Some(now.clone());
+                    device.sent.push(command);
+                }
+                info!("☁️ Sent {} command(s) to {}", lines.len(), serial);
+                lines.join("\n")
+            }
+        }
+        ("POST", "/iclock/devicecmd") => {
+            for line in body.lines().filter(|l| !l.trim().is_empty()) {
+                let fields = parse_pairs(line, '&');
+                let id: u32 = match fields.get("ID").and_then(|id| id.parse().ok()) {
+                    Some(id) => id,
+                    None => continue,
+                };
+                let return_code = fields.get("Return").and_then(|r| r.parse().ok()).unwrap_or(-1);
+                let command = match device.sent.iter().position(|c| c.id == id) {
+                    Some(i) => device.sent.remove(i).command,
+                    None => fields.get("CMD").cloned().unwrap_or_default(),
+                };
+                info!("☁️ {} answered command {} ({}) with {}", serial, id, command, return_code);
+                events.push(AdmsEvent::CommandResult(AdmsCommandResult {
+                    serial: serial.clone(),
+                    id,
+                    return_code,
+                    command,
+                    received_at: now.clone(),
+                }));
+            }
+            "OK".to_string()
+        }
+        (_, "/iclock/ping") => "OK".to_string(),
+        _ => return ("404 Not Found", "Not found".to_string(), events),
+    };
+    ("200 OK", reply, events)
+}
+
+/// Options sent in reply to the initial GET /iclock/cdata
+pub(super) fn handshake(serial: &str, zone: Option<Tz>, stamps: &HashMap<String, String>) -> String {
+    let offset_minutes = match zone {
+        Some(zone) => Utc::now().with_timezone(&zone).offset().fix().local_minus_utc() / 60,
+        None => Local::now().offset().local_minus_utc() / 60,
+    };
+    let stamp = |table: &str| stamps.get(table).map(String::as_str).unwrap_or("None").to_string();
+    [
+        format!("GET OPTION FROM: {}", serial),
+        format!("ATTLOGStamp={}", stamp("ATTLOG")),
+        format!("OPERLOGStamp={}", stamp("OPERLOG")),
+        format!("ATTPHOTOStamp={}", stamp("ATTPHOTO")),
+        "ErrorDelay=30".to_string(),
+        "Delay=10".to_string(),
+        "TransTimes=00:00;14:05".to_string(),
+        "TransInterval=1".to_string(),
+        "TransFlag=TransData AttLog\tOpLog\tEnrollUser\tChgUser".to_string(),
+        format!("TimeZone={}", timezone_hours(offset_minutes)),
+        "Realtime=1".to_string(),
+        "Encrypt=None".to_string(),
+    ].join("\n")
+}
+
+/// UTC offset in hours as the TimeZone option expects it; zones such as +5:30 and
+/// +5:45 are sent as 5.5 and 5.75
+pub(super) fn timezone_hours(offset_minutes: i32) -> String {
+    if offset_minutes % 60 == 0 {
+        (offset_minutes / 60).to_string()
+    } else {
+        (offset_minutes as f64 / 60.0).to_string()
+    }
+}
+
+/// One ATTLOG line: PIN, time, state, verify, workcode, reserved... (tab separated).
+/// None for lines without a numeric PIN, which a record cannot hold.
+pub(super) fn parse_attlog_line(
+    line: &str,
+    users: &HashMap<String, String>,
+    zone: Option<Tz>,
+) -> Option<AttendanceRecord> {
+    let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+    let pin = *fields.first()?;
+    let user_id = pin.parse().ok()?;
+    let raw_time = fields.get(1).copied().unwrap_or("");
+    let field = |i: usize| fields.get(i).and_then(|v| v.parse::<u32>().ok()).unwrap_or(0);
+    let punch = field(2) as u8;
+    let status = field(3) as u8;
+
+    let (timestamp, date, time, timestamp_invalid) = match NaiveDateTime::parse_from_str(raw_time, "%Y-%m-%d %H:%M:%S") {
+        Ok(naive) => {
+            let dt = localize(naive, zone);
+            (dt.to_rfc3339(), dt.format("%Y-%m-%d").to_string(), dt.format("%H:%M:%S").to_string(), false)
+        }
+        Err(_) => (format!("invalid:{}", raw_time), String::new(), String::new(), true),
+    };
+    let user_name = users.get(pin)
+        .filter(|name| !name.is_empty())
+        .cloned()
+        .unwrap_or_else(|| format!("ID: {}", pin));
+
+    Some(AttendanceRecord {
+        user_id,
+        user_name,
+        timestamp,
+        status,
+        punch,
+        date,
+        time,
+        verify: VerifyMethod::from_raw(status),
+        punch_state: PunchState::from_raw(punch),
+        workcode: field(4),
+        timestamp_invalid,
+    })
+}
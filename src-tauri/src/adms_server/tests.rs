@@ -0,0 +1,184 @@
+use super::*;
+use crate::device_settings::DeviceSettings;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::zkteco_client::{PunchState, VerifyMethod};
+
+struct Harness {
+    server: AdmsServer,
+    events: Arc<Mutex<Vec<AdmsEvent>>>,
+}
+
+async fn start(name: &str) -> Harness {
+    let path = std::env::temp_dir().join(format!("adms-settings-{}-{}.json", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    let settings = Arc::new(DeviceSettingsStore::load(path));
+    let registered = DeviceSettings { push_allowed: true, ..DeviceSettings::default() };
+    for serial in ["PUSH001", "PUSH002", "PUSH003"] {
+        settings.set(serial, registered.clone()).unwrap();
+    }
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let server = AdmsServer::start("127.0.0.1:0".parse().unwrap(), settings, move |e| sink.lock().unwrap().push(e))
+        .await
+        .unwrap();
+    Harness { server, events }
+}
+
+/// Send one request on a fresh connection and return (status line, body)
+async fn request(port: u16, method: &str, target: &str, body: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: server\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method, target, body.len(), body
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+#[tokio::test]
+async fn handshake_then_punches_with_names() {
+    let h = start("punches").await;
+    let port = h.server.port();
+
+    let (status, options) = request(port, "GET", "/iclock/cdata?SN=PUSH001&options=all&pushver=2.4.1", "").await;
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(options.starts_with("GET OPTION FROM: PUSH001\n"));
+    assert!(options.contains("Realtime=1"));
+
+    let operlog = "USER PIN=1001\tName=Alice\tPri=0\tPasswd=\tCard=\tGrp=1\nOPLOG 4\t0\t2024-01-15 08:00:00\t0\t0\t0\t0\n";
+    let (_, reply) = request(port, "POST", "/iclock/cdata?SN=PUSH001&table=OPERLOG&Stamp=1", operlog).await;
+    assert_eq!(reply, "OK: 2");
+
+    let attlog = "1001\t2024-01-15 09:00:00\t0\t1\t0\t0\t0\n1002\t2024-01-15 18:02:00\t1\t15\t7\t0\t0\n";
+    let (_, reply) = request(port, "POST", "/iclock/cdata?SN=PUSH001&table=ATTLOG&Stamp=2", attlog).await;
+    assert_eq!(reply, "OK: 2");
+
+    let events = h.events.lock().unwrap();
+    let batch = events.iter().find_map(|e| match e {
+        AdmsEvent::Punches(batch) => Some(batch),
+        _ => None,
+    }).unwrap();
+    assert_eq!(batch.device_serial, "PUSH001");
+    assert_eq!(batch.records[0].user_name, "Alice");
+    assert_eq!(batch.records[0].date, "2024-01-15");
+    assert_eq!(batch.records[0].verify, VerifyMethod::Fingerprint);
+    assert_eq!(batch.records[1].user_name, "ID: 1002");
+    assert_eq!(batch.records[1].punch_state, PunchState::CheckOut);
+    assert_eq!(batch.records[1].verify, VerifyMethod::Face);
+    assert_eq!(batch.records[1].workcode, 7);
+
+    let devices = h.server.devices();
+    assert_eq!(devices[0].records_received, 2);
+    assert_eq!(devices[0].push_version.as_deref(), Some("2.4.1"));
+}
+
+#[tokio::test]
+async fn queued_command_is_delivered_and_answered() {
+    let h = start("commands").await;
+    let port = h.server.port();
+
+    assert!(h.server.queue_command("PUSH002", "REBOOT").is_err());
+    request(port, "GET", "/iclock/cdata?SN=PUSH002&options=all", "").await;
+    let id = h.server.queue_command("PUSH002", "REBOOT").unwrap();
+
+    let (_, reply) = request(port, "GET", "/iclock/getrequest?SN=PUSH002", "").await;
+    assert_eq!(reply, format!("C:{}:REBOOT", id));
+    let (_, reply) = request(port, "GET", "/iclock/getrequest?SN=PUSH002", "").await;
+    assert_eq!(reply, "OK");
+
+    request(port, "POST", "/iclock/devicecmd?SN=PUSH002", &format!("ID={}&Return=0&CMD=REBOOT", id)).await;
+    let events = h.events.lock().unwrap();
+    assert!(events.iter().any(|e| matches!(e, AdmsEvent::CommandResult(r) if r.id == id && r.return_code == 0)));
+}
+
+#[tokio::test]
+async fn request_without_serial_is_rejected() {
+    let h = start("no-sn").await;
+    let (status, _) = request(h.server.port(), "GET", "/iclock/cdata?options=all", "").await;
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+}
+
+#[tokio::test]
+async fn handshake_returns_the_last_upload_stamps() {
+    let h = start("stamps").await;
+    let port = h.server.port();
+
+    let (_, options) = request(port, "GET", "/iclock/cdata?SN=PUSH003&options=all", "").await;
+    assert!(options.contains("ATTLOGStamp=None\n"));
+
+    request(port, "POST", "/iclock/cdata?SN=PUSH003&table=ATTLOG&Stamp=9912", "1001\t2024-01-15 09:00:00\t0\t1\t0\n").await;
+    request(port, "POST", "/iclock/cdata?SN=PUSH003&table=OPERLOG&Stamp=77", "OPLOG 4\t0\t2024-01-15 08:00:00\t0\t0\t0\t0\n").await;
+    let (_, options) = request(port, "GET", "/iclock/cdata?SN=PUSH003&options=all", "").await;
+    assert!(options.contains("ATTLOGStamp=9912\n"), "{}", options);
+    assert!(options.contains("OPERLOGStamp=77\n"), "{}", options);
+    assert!(options.contains("ATTPHOTOStamp=None\n"), "{}", options);
+}
+
+#[tokio::test]
+async fn unregistered_serial_is_refused() {
+    let h = start("unregistered").await;
+    let port = h.server.port();
+
+    let (status, _) = request(port, "GET", "/iclock/cdata?SN=STRANGER&options=all", "").await;
+    assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+    let (status, _) = request(port, "POST", "/iclock/cdata?SN=STRANGER&table=ATTLOG&Stamp=1", "1001\t2024-01-15 09:00:00\t0\t1\n").await;
+    assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+    assert!(h.server.devices().is_empty());
+    assert!(h.events.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn upload_with_unreadable_pin_is_not_acknowledged() {
+    let h = start("bad-pin").await;
+    let port = h.server.port();
+
+    let attlog = "1001\t2024-01-15 09:00:00\t0\t1\nA12\t2024-01-15 09:01:00\t0\t1\n";
+    let (status, _) = request(port, "POST", "/iclock/cdata?SN=PUSH003&table=ATTLOG&Stamp=40", attlog).await;
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    assert!(!h.events.lock().unwrap().iter().any(|e| matches!(e, AdmsEvent::Punches(_))));
+
+    // The stamp stays put, so the terminal sends the rows again
+    let (_, options) = request(port, "GET", "/iclock/cdata?SN=PUSH003&options=all", "").await;
+    assert!(options.contains("ATTLOGStamp=None\n"), "{}", options);
+    assert_eq!(h.server.devices()[0].records_received, 0);
+}
+
+#[tokio::test]
+async fn overlong_header_line_is_refused() {
+    let h = start("long-header").await;
+    let mut stream = TcpStream::connect(("127.0.0.1", h.server.port())).await.unwrap();
+    let header = format!("GET /iclock/ping HTTP/1.1\r\nX-Padding: {}", "a".repeat(MAX_HEADER_LINE * 4));
+    let _ = stream.write_all(header.as_bytes()).await;
+
+    // The connection is dropped without waiting for the line to end
+    let mut response = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut response)).await.unwrap();
+    assert!(response.is_empty());
+}
+
+#[test]
+fn fractional_zones_are_sent_as_fractional_hours() {
+    let none = HashMap::new();
+    assert!(handshake("X", Some(chrono_tz::Asia::Kolkata), &none).contains("\nTimeZone=5.5\n"));
+    assert!(handshake("X", Some(chrono_tz::Asia::Kathmandu), &none).contains("\nTimeZone=5.75\n"));
+    assert!(handshake("X", Some(chrono_tz::Asia::Tokyo), &none).contains("\nTimeZone=9\n"));
+    assert_eq!(timezone_hours(-210), "-3.5");
+}
+
+#[test]
+fn push_lines_without_numeric_pin_are_skipped() {
+    assert!(parse_attlog_line("A12\t2024-01-15 09:00:00\t0\t1", &HashMap::new(), None).is_none());
+    assert!(parse_attlog_line("\t2024-01-15 09:00:00\t0\t1", &HashMap::new(), None).is_none());
+    assert_eq!(parse_attlog_line("1001\t2024-01-15 09:00:00\t0\t1", &HashMap::new(), None).unwrap().user_id, 1001);
+}
+
+#[test]
+fn invalid_push_time_is_flagged() {
+    let record = parse_attlog_line("7\t2024-02-31 09:00:00\t0\t1", &HashMap::new(), None).unwrap();
+    assert!(record.timestamp_invalid);
+    assert_eq!(record.timestamp, "invalid:2024-02-31 09:00:00");
+}
@@ -0,0 +1,235 @@
+//! Local attendance database (SQLite) - keeps punch history across fetches
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use rusqlite::{params, params_from_iter, Connection};
+use log::info;
+use crate::zkteco_client::{AttendanceRecord, PunchState, VerifyMethod};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredAttendanceRecord {
+    pub device_serial: String,
+    #[serde(flatten)]
+    pub record: AttendanceRecord,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttendanceQuery {
+    pub from_date: Option<String>,      // YYYY-MM-DD, inclusive
+    pub to_date: Option<String>,        // YYYY-MM-DD, inclusive
+    pub user_id: Option<u32>,
+    pub device_serial: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestSummary {
+    pub device_serial: String,
+    pub received: usize,
+    pub inserted: usize,
+    pub duplicates: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredDeviceSummary {
+    pub device_serial: String,
+    pub record_count: u64,
+    pub first_timestamp: Option<String>,
+    pub last_timestamp: Option<String>,
+}
+
+pub struct AttendanceStore {
+    conn: Mutex<Connection>,
+}
+
+const LOCAL_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+impl AttendanceStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open attendance database: {}", e))?;
+
+        // Punches are keyed on the device's wall-clock time, which is what the device
+        // itself stores; the offset it was read with and the UTC instant are kept
+        // alongside, so changing a device's zone does not store its whole log again.
+        // Invalid device times keep their raw "invalid:..." text and have no offset.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS attendance (
+                device_serial      TEXT NOT NULL,
+                user_id            INTEGER NOT NULL,
+                local_time         TEXT NOT NULL,
+                utc_offset_minutes INTEGER,
+                utc_time           TEXT,
+                user_name          TEXT NOT NULL,
+                status             INTEGER NOT NULL,
+                punch              INTEGER NOT NULL,
+                date               TEXT NOT NULL,
+                time               TEXT NOT NULL,
+                workcode           INTEGER NOT NULL,
+                fetched_at         TEXT NOT NULL,
+                PRIMARY KEY (device_serial, user_id, local_time)
+            );
+            CREATE INDEX IF NOT EXISTS idx_attendance_date ON attendance(date);
+            CREATE INDEX IF NOT EXISTS idx_attendance_user ON attendance(user_id);
+            CREATE INDEX IF NOT EXISTS idx_attendance_utc ON attendance(utc_time);",
+        )
+        .map_err(|e| format!("Failed to initialise attendance database: {}", e))?;
+
+        info!("📚 Attendance database: {}", path.display());
+        Ok(AttendanceStore { conn: Mutex::new(conn) })
+    }
+
+    /// Device-local time, UTC offset in minutes and UTC instant of a record's timestamp
+    /// (the raw text and no offset for invalid device times)
+    fn split_timestamp(timestamp: &str) -> (String, Option<i32>, Option<String>) {
+        match DateTime::parse_from_rfc3339(timestamp) {
+            Ok(dt) => (
+                dt.naive_local().format(LOCAL_TIME_FORMAT).to_string(),
+                Some(dt.offset().local_minus_utc() / 60),
+                Some(dt.with_timezone(&Utc).format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            ),
+            Err(_) => (timestamp.to_string(), None, None),
+        }
+    }
+
+    /// Inverse of `split_timestamp`
+    fn join_timestamp(local_time: String, utc_offset_minutes: Option<i32>) -> String {
+        let offset = utc_offset_minutes.and_then(|m| FixedOffset::east_opt(m * 60));
+        match (offset, NaiveDateTime::parse_from_str(&local_time, LOCAL_TIME_FORMAT)) {
+            (Some(offset), Ok(naive)) => DateTime::<FixedOffset>::from_naive_utc_and_offset(naive - offset, offset).to_rfc3339(),
+            _ => local_time,
+        }
+    }
+
+    /// Insert records for a device, silently skipping ones already stored
+    pub fn ingest(&self, device_serial: &str, records: &[AttendanceRecord]) -> Result<IngestSummary, String> {
+        let mut conn = self.conn.lock().map_err(|e| format!("Database lock poisoned: {}", e))?;
+        let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let fetched_at = chrono::Local::now().to_rfc3339();
+
+        let mut inserted = 0;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT OR IGNORE INTO attendance
+                        (device_serial, user_id, local_time, utc_offset_minutes, utc_time,
+                         user_name, status, punch, date, time, workcode, fetched_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                )
+                .map_err(|e| format!("Failed to prepare insert: {}", e))?;
+
+            for r in records {
+                let (local_time, utc_offset_minutes, utc_time) = Self::split_timestamp(&r.timestamp);
+                inserted += stmt
+                    .execute(params![
+                        device_serial, r.user_id, local_time, utc_offset_minutes, utc_time, r.user_name,
+                        r.status, r.punch, r.date, r.time, r.workcode, fetched_at,
+                    ])
+                    .map_err(|e| format!("Failed to insert record: {}", e))?;
+            }
+        }
+        tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+
+        info!("📚 Stored {} new record(s) for {} ({} duplicates)",
+            inserted, device_serial, records.len() - inserted);
+
+        Ok(IngestSummary {
+            device_serial: device_serial.to_string(),
+            received: records.len(),
+            inserted,
+            duplicates: records.len() - inserted,
+        })
+    }
+
+    pub fn query(&self, query: &AttendanceQuery) -> Result<Vec<StoredAttendanceRecord>, String> {
+        let mut sql = String::from(
+            "SELECT device_serial, user_id, user_name, local_time, status, punch, date, time, workcode, utc_offset_minutes
+             FROM attendance WHERE 1 = 1",
+        );
+        let mut args: Vec<String> = Vec::new();
+
+        if let Some(from) = &query.from_date {
+            args.push(from.clone());
+            sql.push_str(&format!(" AND date >= ?{}", args.len()));
+        }
+        if let Some(to) = &query.to_date {
+            args.push(to.clone());
+            sql.push_str(&format!(" AND date <= ?{}", args.len()));
+        }
+        if let Some(user_id) = query.user_id {
+            args.push(user_id.to_string());
+            sql.push_str(&format!(" AND user_id = CAST(?{} AS INTEGER)", args.len()));
+        }
+        if let Some(serial) = &query.device_serial {
+            args.push(serial.clone());
+            sql.push_str(&format!(" AND device_serial = ?{}", args.len()));
+        }
+        // Invalid device times have no instant and go last
+        sql.push_str(" ORDER BY utc_time IS NULL, utc_time, local_time, device_serial, user_id");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let conn = self.conn.lock().map_err(|e| format!("Database lock poisoned: {}", e))?;
+        let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let rows = stmt
+            .query_map(params_from_iter(args.iter()), |row| {
+                let status: u8 = row.get(4)?;
+                let punch: u8 = row.get(5)?;
+                let timestamp = Self::join_timestamp(row.get(3)?, row.get(9)?);
+                Ok(StoredAttendanceRecord {
+                    device_serial: row.get(0)?,
+                    record: AttendanceRecord {
+                        user_id: row.get(1)?,
+                        user_name: row.get(2)?,
+                        timestamp_invalid: timestamp.starts_with("invalid:"),
+                        timestamp,
+                        status,
+                        punch,
+                        date: row.get(6)?,
+                        time: row.get(7)?,
+                        verify: VerifyMethod::from_raw(status),
+                        punch_state: PunchState::from_raw(punch),
+                        workcode: row.get(8)?,
+                    },
+                })
+            })
+            .map_err(|e| format!("Query failed: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read row: {}", e))
+    }
+
+    /// Devices that have stored records, with their record counts and time span
+    pub fn devices(&self) -> Result<Vec<StoredDeviceSummary>, String> {
+        let conn = self.conn.lock().map_err(|e| format!("Database lock poisoned: {}", e))?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT device_serial, COUNT(*), MIN(utc_time), MAX(utc_time)
+                 FROM attendance GROUP BY device_serial ORDER BY device_serial",
+            )
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(StoredDeviceSummary {
+                    device_serial: row.get(0)?,
+                    record_count: row.get(1)?,
+                    first_timestamp: row.get(2)?,
+                    last_timestamp: row.get(3)?,
+                })
+            })
+            .map_err(|e| format!("Query failed: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read row: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,111 @@
+use super::*;
+
+fn open(name: &str) -> AttendanceStore {
+    let path = std::env::temp_dir().join(format!("attendance-{}-{}.db", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    AttendanceStore::open(&path).unwrap()
+}
+
+fn punch(user_id: u32, timestamp: &str) -> AttendanceRecord {
+    AttendanceRecord {
+        user_id,
+        user_name: format!("User {}", user_id),
+        timestamp: timestamp.to_string(),
+        status: 1,
+        punch: 0,
+        date: timestamp.get(..10).unwrap_or_default().to_string(),
+        time: timestamp.get(11..19).unwrap_or_default().to_string(),
+        verify: VerifyMethod::from_raw(1),
+        punch_state: PunchState::from_raw(0),
+        workcode: 0,
+        timestamp_invalid: timestamp.starts_with("invalid:"),
+    }
+}
+
+fn timestamps(store: &AttendanceStore) -> Vec<String> {
+    store.query(&AttendanceQuery::default()).unwrap()
+        .into_iter()
+        .map(|r| r.record.timestamp)
+        .collect()
+}
+
+fn user_ids(store: &AttendanceStore, query: AttendanceQuery) -> Vec<(String, u32)> {
+    store.query(&query).unwrap()
+        .into_iter()
+        .map(|r| (r.device_serial, r.record.user_id))
+        .collect()
+}
+
+#[test]
+fn ingesting_again_skips_stored_punches() {
+    let store = open("dedup");
+    let batch = [punch(1, "2024-01-15T09:00:00+01:00"), punch(2, "2024-01-15T09:00:00+01:00")];
+    let first = store.ingest("DEV1", &batch).unwrap();
+    assert_eq!((first.received, first.inserted, first.duplicates), (2, 2, 0));
+    
+    let again = store.ingest("DEV1", &[batch[1].clone(), punch(1, "2024-01-15T17:00:00+01:00")]).unwrap();
+    assert_eq!((again.received, again.inserted, again.duplicates), (2, 1, 1));
+    // The same punch from another device is a different record
+    assert_eq!(store.ingest("DEV2", &batch[..1]).unwrap().inserted, 1);
+    assert_eq!(timestamps(&store).len(), 4);
+    
+    let devices = store.devices().unwrap();
+    assert_eq!(devices.iter().map(|d| d.record_count).collect::<Vec<_>>(), [3, 1]);
+}
+
+#[test]
+fn query_filters_by_date_user_and_device() {
+    let store = open("filters");
+    store.ingest("DEV1", &[
+        punch(1, "2024-01-14T09:00:00+00:00"),
+        punch(1, "2024-01-15T09:00:00+00:00"),
+        punch(2, "2024-01-16T09:00:00+00:00"),
+    ]).unwrap();
+    store.ingest("DEV2", &[punch(2, "2024-01-15T10:00:00+00:00")]).unwrap();
+    let dev = |serial: &str, user_id: u32| (serial.to_string(), user_id);
+    
+    let from = AttendanceQuery { from_date: Some("2024-01-15".into()), ..Default::default() };
+    assert_eq!(user_ids(&store, from), [dev("DEV1", 1), dev("DEV2", 2), dev("DEV1", 2)]);
+    let to = AttendanceQuery { to_date: Some("2024-01-15".into()), ..Default::default() };
+    assert_eq!(user_ids(&store, to), [dev("DEV1", 1), dev("DEV1", 1), dev("DEV2", 2)]);
+    let user = AttendanceQuery { user_id: Some(2), ..Default::default() };
+    assert_eq!(user_ids(&store, user), [dev("DEV2", 2), dev("DEV1", 2)]);
+    let device = AttendanceQuery { device_serial: Some("DEV2".into()), ..Default::default() };
+    assert_eq!(user_ids(&store, device), [dev("DEV2", 2)]);
+    let limited = AttendanceQuery { limit: Some(2), ..Default::default() };
+    assert_eq!(user_ids(&store, limited), [dev("DEV1", 1), dev("DEV1", 1)]);
+    let combined = AttendanceQuery {
+        from_date: Some("2024-01-15".into()),
+        to_date: Some("2024-01-15".into()),
+        user_id: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(user_ids(&store, combined), [dev("DEV1", 1)]);
+}
+
+#[test]
+fn changing_the_device_zone_does_not_store_punches_again() {
+    let store = open("zone-change");
+    store.ingest("DEV1", &[punch(1, "2024-01-15T09:00:00+05:30")]).unwrap();
+    
+    let summary = store.ingest("DEV1", &[punch(1, "2024-01-15T09:00:00+01:00")]).unwrap();
+    assert_eq!(summary.inserted, 0);
+    assert_eq!(timestamps(&store), ["2024-01-15T09:00:00+05:30"]);
+}
+
+#[test]
+fn punches_are_ordered_by_instant_across_offsets() {
+    let store = open("ordering");
+    store.ingest("DEV1", &[punch(1, "2024-01-15T00:00:00-05:00"), punch(1, "invalid:00112233")]).unwrap();
+    store.ingest("DEV2", &[punch(2, "2024-01-15T05:00:00+01:00")]).unwrap();
+    store.ingest("DEV3", &[punch(3, "2024-01-15T09:00:00+05:30")]).unwrap();
+    
+    assert_eq!(timestamps(&store), [
+        "2024-01-15T09:00:00+05:30",
+        "2024-01-15T05:00:00+01:00",
+        "2024-01-15T00:00:00-05:00",
+        "invalid:00112233",
+    ]);
+    let devices = store.devices().unwrap();
+    assert_eq!(devices[0].first_timestamp.as_deref(), Some("2024-01-15T05:00:00Z"));
+}
@@ -0,0 +1,10 @@
+//! Tauri commands, grouped by feature
+
+pub mod attendance;
+pub mod live;
+pub mod users;
+pub mod device;
+pub mod settings;
+pub mod media;
+pub mod documents;
+pub mod ai;
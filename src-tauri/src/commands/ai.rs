@@ -0,0 +1,22 @@
+//! AI assistant commands
+
+use crate::ai_assistant::{AIProvider, ChatRequest, ChatResponse};
+use crate::ai_assistant;
+
+#[tauri::command]
+pub fn ai_get_providers() -> Vec<AIProvider> {
+    ai_assistant::get_providers()
+}
+
+#[tauri::command]
+pub async fn ai_chat(
+    request: ChatRequest,
+    api_key: Option<String>,
+) -> Result<ChatResponse, String> {
+    ai_assistant::chat(request, api_key).await
+}
+
+#[tauri::command]
+pub fn ai_get_system_prompt() -> String {
+    ai_assistant::get_system_prompt()
+}
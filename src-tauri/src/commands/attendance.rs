@@ -0,0 +1,104 @@
+//! Attendance download and query commands
+
+use std::sync::Arc;
+use tauri::State;
+use crate::device_scanner::{scan_network, BiometricDevice};
+use crate::zkteco_client::{
+    connect_and_fetch_attendance, connect_and_fetch_attendance_since, device_key,
+    AttendanceResponse, DeviceTarget, FleetFetchResponse, IncrementalAttendanceResponse,
+};
+use crate::watermark_store::WatermarkStore;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use crate::attendance_store::{
+    AttendanceQuery, AttendanceStore, StoredAttendanceRecord, StoredDeviceSummary,
+};
+use crate::zkteco_client;
+
+/// Scan the LAN for terminals; `password` is tried when reading device info
+#[tauri::command]
+pub async fn scan_for_devices(password: Option<u32>) -> Result<Vec<BiometricDevice>, String> {
+    scan_network(password).await
+}
+
+#[tauri::command]
+pub async fn fetch_attendance(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    store: State<'_, AttendanceStore>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<AttendanceResponse, ZkError> {
+    let response = connect_and_fetch_attendance(&ip, port, password, settings.inner().clone()).await?;
+    store.ingest(&device_key(&response.device_info, &ip, port), &response.records).map_err(ZkError::Other)?;
+    Ok(response)
+}
+
+#[tauri::command]
+pub async fn fetch_attendance_since(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    since: Option<String>,
+    watermarks: State<'_, Arc<WatermarkStore>>,
+    store: State<'_, AttendanceStore>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<IncrementalAttendanceResponse, ZkError> {
+    let since = since
+        .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map_err(|e| ZkError::InvalidArgument(format!("Invalid since timestamp: {}", e))))
+        .transpose()?;
+    let response =
+        connect_and_fetch_attendance_since(&ip, port, password, since, watermarks.inner().clone(), settings.inner().clone()).await?;
+    
+    // Only move the watermark once the records are safely stored
+    store.ingest(&response.device_key, &response.records).map_err(ZkError::Other)?;
+    watermarks.set(&response.device_key, response.watermark.clone()).map_err(ZkError::Other)?;
+    Ok(response)
+}
+
+/// Fetch from several devices concurrently and store each device's records
+#[tauri::command]
+pub async fn fetch_attendance_fleet(
+    devices: Vec<DeviceTarget>,
+    max_concurrent: Option<usize>,
+    store: State<'_, AttendanceStore>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<FleetFetchResponse, ZkError> {
+    let max_concurrent = max_concurrent.unwrap_or(zkteco_client::FLEET_DEFAULT_CONCURRENCY);
+    let mut response = zkteco_client::fetch_attendance_fleet(devices, max_concurrent, settings.inner().clone()).await;
+    
+    for device in response.devices.iter_mut() {
+        if let Some(key) = device.device_key.clone() {
+            let records: Vec<_> = response.records.iter()
+                .filter(|r| r.device_serial == key)
+                .map(|r| r.record.clone())
+                .collect();
+            if let Err(e) = store.ingest(&key, &records) {
+                device.success = false;
+                device.error = Some(format!("Fetched but not stored: {}", e));
+            }
+        }
+    }
+    Ok(response)
+}
+
+#[tauri::command]
+pub fn reset_attendance_watermark(
+    device_key: String,
+    watermarks: State<'_, Arc<WatermarkStore>>,
+) -> Result<(), String> {
+    watermarks.clear(&device_key)
+}
+
+#[tauri::command]
+pub fn query_attendance(
+    query: AttendanceQuery,
+    store: State<'_, AttendanceStore>,
+) -> Result<Vec<StoredAttendanceRecord>, String> {
+    store.query(&query)
+}
+
+#[tauri::command]
+pub fn list_stored_devices(store: State<'_, AttendanceStore>) -> Result<Vec<StoredDeviceSummary>, String> {
+    store.devices()
+}
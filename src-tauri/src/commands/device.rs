@@ -0,0 +1,180 @@
+//! Device clock, options, capacity and control commands
+
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+use crate::zkteco_client::{
+    ClearReceipt, ClockSetReceipt, ClockSyncResult, ControlReceipt, DeviceAction,
+    DeviceOptionsReport, OptionWrite, OptionWriteResult, DeviceCapacity, DeviceClock, DeviceTarget,
+};
+use crate::watermark_store::WatermarkStore;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use crate::attendance_store::AttendanceStore;
+use crate::zkteco_client;
+
+/// Download, store and verify the attendance log, then clear it on the device
+#[tauri::command]
+pub async fn fetch_and_clear_attendance(
+    app: AppHandle,
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    watermarks: State<'_, Arc<WatermarkStore>>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ClearReceipt, ZkError> {
+    let store_handle = app.clone();
+    let persist = move |device_key: &str, records: &[zkteco_client::AttendanceRecord]| {
+        let summary = store_handle.state::<AttendanceStore>().ingest(device_key, records)?;
+        if summary.inserted + summary.duplicates != records.len() {
+            return Err(format!("Stored {} of {} records", summary.inserted + summary.duplicates, records.len()));
+        }
+        Ok(())
+    };
+    
+    let receipt = zkteco_client::fetch_and_clear_attendance(&ip, port, password, settings.inner().clone(), persist).await?;
+    // The device log restarts from zero, so the old watermark no longer applies
+    watermarks.clear(&receipt.device_key).map_err(ZkError::Other)?;
+    Ok(receipt)
+}
+
+/// Reboot a device; refused unless `confirm` is true
+#[tauri::command]
+pub async fn restart_device(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    confirm: bool,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ControlReceipt, ZkError> {
+    zkteco_client::control_device(&ip, port, password, DeviceAction::Restart, confirm, settings.inner().clone()).await
+}
+
+/// Shut a device down (it must be switched on again by hand); refused unless `confirm` is true
+#[tauri::command]
+pub async fn power_off_device(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    confirm: bool,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ControlReceipt, ZkError> {
+    zkteco_client::control_device(&ip, port, password, DeviceAction::PowerOff, confirm, settings.inner().clone()).await
+}
+
+/// Release the door lock for `seconds`; refused unless `confirm` is true
+#[tauri::command]
+pub async fn unlock_door(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    seconds: u32,
+    confirm: bool,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ControlReceipt, ZkError> {
+    let action = DeviceAction::UnlockDoor { seconds };
+    zkteco_client::control_device(&ip, port, password, action, confirm, settings.inner().clone()).await
+}
+
+/// Play a built-in voice prompt to identify a device
+#[tauri::command]
+pub async fn play_test_voice(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    index: Option<u32>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ControlReceipt, ZkError> {
+    let action = DeviceAction::TestVoice { index: index.unwrap_or(0) };
+    zkteco_client::signal_device(&ip, port, password, action, settings.inner().clone()).await
+}
+
+/// Make a device reload its users and settings
+#[tauri::command]
+pub async fn refresh_device_data(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ControlReceipt, ZkError> {
+    zkteco_client::signal_device(&ip, port, password, DeviceAction::RefreshData, settings.inner().clone()).await
+}
+
+/// Read the given device options (e.g. "LockOn", "VerifyMode")
+#[tauri::command]
+pub async fn get_device_options(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    keys: Vec<String>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<DeviceOptionsReport, ZkError> {
+    zkteco_client::read_device_options(&ip, port, password, keys, settings.inner().clone()).await
+}
+
+/// Write device options and return, per option, the value before and after and whether it took
+#[tauri::command]
+pub async fn set_device_options(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    options: Vec<OptionWrite>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<OptionWriteResult, ZkError> {
+    zkteco_client::write_device_options(&ip, port, password, options, settings.inner().clone()).await
+}
+
+/// Read every known option of a device for a settings audit
+#[tauri::command]
+pub async fn dump_device_options(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<DeviceOptionsReport, ZkError> {
+    zkteco_client::dump_device_options(&ip, port, password, settings.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn get_device_capacity(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<DeviceCapacity, ZkError> {
+    zkteco_client::get_device_capacity(&ip, port, password, settings.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn get_device_time(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<DeviceClock, ZkError> {
+    zkteco_client::get_device_time(&ip, port, password, settings.inner().clone()).await
+}
+
+/// Set the device clock to `time` ("YYYY-MM-DD HH:MM:SS", device local) or to the current time
+#[tauri::command]
+pub async fn set_device_time(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    time: Option<String>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ClockSetReceipt, ZkError> {
+    let time = time
+        .map(|t| chrono::NaiveDateTime::parse_from_str(&t, "%Y-%m-%d %H:%M:%S").map_err(|e| ZkError::InvalidArgument(format!("Invalid time: {}", e))))
+        .transpose()?;
+    zkteco_client::set_device_time(&ip, port, password, time, settings.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn sync_device_clocks(
+    devices: Vec<DeviceTarget>,
+    max_concurrent: Option<usize>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<Vec<ClockSyncResult>, ZkError> {
+    let max_concurrent = max_concurrent.unwrap_or(zkteco_client::FLEET_DEFAULT_CONCURRENCY);
+    Ok(zkteco_client::sync_device_clocks(devices, max_concurrent, settings.inner().clone()).await)
+}
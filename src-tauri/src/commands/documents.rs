@@ -0,0 +1,91 @@
+//! Document commands, external tools and bundled
+
+use crate::document_converter::ToolStatus;
+use crate::document_converter;
+use crate::bundled_converter;
+
+#[tauri::command]
+pub fn check_document_tools() -> Vec<ToolStatus> {
+    document_converter::check_tools()
+}
+
+#[tauri::command]
+pub async fn document_convert_office(
+    input_path: String,
+    output_format: String,
+    output_dir: String,
+) -> Result<document_converter::ConversionResult, String> {
+    document_converter::convert_with_libreoffice(input_path, output_format, output_dir).await
+}
+
+#[tauri::command]
+pub async fn document_convert_pandoc(
+    input_path: String,
+    output_path: String,
+    from_format: Option<String>,
+    to_format: Option<String>,
+) -> Result<document_converter::ConversionResult, String> {
+    document_converter::convert_with_pandoc(input_path, output_path, from_format, to_format).await
+}
+
+// ============================================================================
+// Bundled Document Commands (No external dependencies!)
+// ============================================================================
+
+#[tauri::command]
+pub fn bundled_get_doc_info(file_path: String) -> Result<bundled_converter::DocumentInfo, String> {
+    bundled_converter::get_document_info(&file_path)
+}
+
+#[tauri::command]
+pub fn bundled_merge_pdfs(
+    input_paths: Vec<String>,
+    output_path: String,
+) -> Result<bundled_converter::ConversionResult, String> {
+    bundled_converter::merge_pdfs(input_paths, output_path)
+}
+
+#[tauri::command]
+pub fn bundled_excel_to_csv(
+    input_path: String,
+    output_path: String,
+    sheet_index: Option<usize>,
+) -> Result<bundled_converter::ConversionResult, String> {
+    bundled_converter::excel_to_csv(input_path, output_path, sheet_index)
+}
+
+#[tauri::command]
+pub fn bundled_csv_to_json(
+    input_path: String,
+    output_path: String,
+) -> Result<bundled_converter::ConversionResult, String> {
+    bundled_converter::csv_to_json(input_path, output_path)
+}
+
+#[tauri::command]
+pub fn bundled_json_to_csv(
+    input_path: String,
+    output_path: String,
+) -> Result<bundled_converter::ConversionResult, String> {
+    bundled_converter::json_to_csv(input_path, output_path)
+}
+
+#[tauri::command]
+pub fn bundled_convert_image(
+    input_path: String,
+    output_path: String,
+    quality: Option<u8>,
+) -> Result<bundled_converter::ConversionResult, String> {
+    bundled_converter::convert_image_format(input_path, output_path, quality)
+}
+
+#[tauri::command]
+pub fn bundled_resize_image(
+    input_path: String,
+    output_path: String,
+    width: u32,
+    height: u32,
+    maintain_aspect: bool,
+) -> Result<bundled_converter::ConversionResult, String> {
+    bundled_converter::resize_image(input_path, output_path, width, height, maintain_aspect)
+}
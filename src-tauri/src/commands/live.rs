@@ -0,0 +1,154 @@
+//! Live capture and ADMS push server commands
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use crate::zkteco_client::{live_capture, LiveCaptureEvent};
+use crate::device_settings::DeviceSettingsStore;
+use crate::adms_server::{AdmsDeviceStatus, AdmsEvent, AdmsServer};
+use crate::adms_server;
+use crate::attendance_store::AttendanceStore;
+
+/// Running live capture sessions, keyed by "ip:port"
+#[derive(Default)]
+pub struct LiveCaptures(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+/// Start streaming punches from a device as `attendance-punch` events
+/// (connection changes are reported as `attendance-live-status`)
+#[tauri::command]
+pub fn start_live_capture(
+    app: AppHandle,
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    captures: State<'_, LiveCaptures>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<(), String> {
+    let key = format!("{}:{}", ip, port);
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let mut running = captures.0.lock().map_err(|e| format!("Lock poisoned: {}", e))?;
+        if running.contains_key(&key) {
+            return Err(format!("Live capture already running for {}", key));
+        }
+        running.insert(key.clone(), Arc::clone(&stop));
+    }
+    
+    let settings = settings.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let emitter = app.clone();
+        let on_event = move |event: LiveCaptureEvent| match event {
+            LiveCaptureEvent::Punch(punch) => {
+                let store = emitter.state::<AttendanceStore>();
+                if let Err(e) = store.ingest(&punch.device_key, std::slice::from_ref(&punch.record)) {
+                    log::warn!("Failed to store live punch: {}", e);
+                }
+                let _ = emitter.emit("attendance-punch", punch);
+            }
+            LiveCaptureEvent::Status(status) => {
+                let _ = emitter.emit("attendance-live-status", status);
+            }
+        };
+        
+        if let Err(e) = live_capture(&ip, port, password, Arc::clone(&stop), settings, on_event).await {
+            log::warn!("Live capture for {} ended with error: {}", key, e);
+        }
+        
+        // Only unregister our own session (a new one may have started after a stop)
+        let captures = app.state::<LiveCaptures>();
+        if let Ok(mut running) = captures.0.lock() {
+            if running.get(&key).map(|s| Arc::ptr_eq(s, &stop)).unwrap_or(false) {
+                running.remove(&key);
+            }
+        };
+    });
+    
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_live_capture(ip: String, port: u16, captures: State<'_, LiveCaptures>) -> Result<(), String> {
+    let key = format!("{}:{}", ip, port);
+    let mut running = captures.0.lock().map_err(|e| format!("Lock poisoned: {}", e))?;
+    match running.remove(&key) {
+        Some(stop) => {
+            stop.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err(format!("No live capture running for {}", key)),
+    }
+}
+
+/// The ADMS push server, when running
+#[derive(Default)]
+pub struct AdmsServers(Mutex<Option<AdmsServer>>);
+
+/// Start accepting push-mode terminals; pushed punches are stored and emitted as
+/// `adms-punches` (terminal check-ins as `adms-device`, command answers as `adms-command-result`).
+/// Only terminals whose serial has `push_allowed` in its device settings are served.
+#[tauri::command]
+pub async fn start_adms_server(
+    app: AppHandle,
+    port: Option<u16>,
+    servers: State<'_, AdmsServers>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<u16, String> {
+    if servers.0.lock().map_err(|e| format!("Lock poisoned: {}", e))?.is_some() {
+        return Err("ADMS server is already running".to_string());
+    }
+    
+    let bind = std::net::SocketAddr::from(([0, 0, 0, 0], port.unwrap_or(adms_server::ADMS_DEFAULT_PORT)));
+    let emitter = app.clone();
+    let server = AdmsServer::start(bind, settings.inner().clone(), move |event| match event {
+        AdmsEvent::Punches(batch) => {
+            let store = emitter.state::<AttendanceStore>();
+            if let Err(e) = store.ingest(&batch.device_serial, &batch.records) {
+                log::warn!("Failed to store pushed punches from {}: {}", batch.device_serial, e);
+            }
+            let _ = emitter.emit("adms-punches", batch);
+        }
+        AdmsEvent::Device(status) => {
+            let _ = emitter.emit("adms-device", status);
+        }
+        AdmsEvent::CommandResult(result) => {
+            let _ = emitter.emit("adms-command-result", result);
+        }
+    }).await?;
+    
+    let port = server.port();
+    let mut running = servers.0.lock().map_err(|e| format!("Lock poisoned: {}", e))?;
+    if running.is_some() {
+        return Err("ADMS server is already running".to_string());
+    }
+    *running = Some(server);
+    Ok(port)
+}
+
+#[tauri::command]
+pub fn stop_adms_server(servers: State<'_, AdmsServers>) -> Result<(), String> {
+    let mut running = servers.0.lock().map_err(|e| format!("Lock poisoned: {}", e))?;
+    match running.take() {
+        Some(server) => {
+            server.stop();
+            Ok(())
+        }
+        None => Err("ADMS server is not running".to_string()),
+    }
+}
+
+/// Terminals that have contacted the ADMS server
+#[tauri::command]
+pub fn list_adms_devices(servers: State<'_, AdmsServers>) -> Result<Vec<AdmsDeviceStatus>, String> {
+    let running = servers.0.lock().map_err(|e| format!("Lock poisoned: {}", e))?;
+    Ok(running.as_ref().map(|s| s.devices()).unwrap_or_default())
+}
+
+/// Queue a raw iclock command (e.g. "REBOOT") for a push-mode terminal; returns the command id
+#[tauri::command]
+pub fn queue_adms_command(serial: String, command: String, servers: State<'_, AdmsServers>) -> Result<u32, String> {
+    let running = servers.0.lock().map_err(|e| format!("Lock poisoned: {}", e))?;
+    running.as_ref()
+        .ok_or("ADMS server is not running")?
+        .queue_command(&serial, &command)
+}
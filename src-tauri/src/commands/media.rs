@@ -0,0 +1,70 @@
+//! Media, video and image commands (FFmpeg)
+
+use crate::media_converter::{VideoConvertOptions, ImageConvertOptions, ConversionResult, MediaInfo};
+use crate::media_converter;
+
+#[tauri::command]
+pub fn check_ffmpeg_status() -> Result<String, String> {
+    media_converter::check_ffmpeg()
+}
+
+#[tauri::command]
+pub async fn get_media_information(file_path: String) -> Result<MediaInfo, String> {
+    media_converter::get_media_info(&file_path).await
+}
+
+// ============================================================================
+// Video Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn video_convert(options: VideoConvertOptions) -> Result<ConversionResult, String> {
+    media_converter::convert_video(options).await
+}
+
+#[tauri::command]
+pub async fn video_compress(
+    input_path: String,
+    output_path: String,
+    target_bitrate: Option<String>,
+) -> Result<ConversionResult, String> {
+    media_converter::compress_video(input_path, output_path, target_bitrate).await
+}
+
+#[tauri::command]
+pub async fn video_extract_audio(
+    input_path: String,
+    output_path: String,
+    format: String,
+) -> Result<ConversionResult, String> {
+    media_converter::extract_audio(input_path, output_path, format).await
+}
+
+// ============================================================================
+// Image Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn image_convert(options: ImageConvertOptions) -> Result<ConversionResult, String> {
+    media_converter::convert_image(options).await
+}
+
+#[tauri::command]
+pub async fn image_compress(
+    input_path: String,
+    output_path: String,
+    quality: u32,
+) -> Result<ConversionResult, String> {
+    media_converter::compress_image(input_path, output_path, quality).await
+}
+
+#[tauri::command]
+pub async fn image_resize(
+    input_path: String,
+    output_path: String,
+    width: u32,
+    height: u32,
+    maintain_aspect: bool,
+) -> Result<ConversionResult, String> {
+    media_converter::resize_image(input_path, output_path, width, height, maintain_aspect).await
+}
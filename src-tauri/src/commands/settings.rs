@@ -0,0 +1,57 @@
+//! Device settings and protocol trace commands
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+use crate::zkteco_client::AttendanceResponse;
+use crate::device_settings::{DeviceSettings, DeviceSettingsStore};
+use crate::zk_error::ZkError;
+use crate::zkteco_client;
+use crate::zk_trace;
+
+/// Stored overrides for a device (keyed like attendance: serial number, or ip:port)
+#[tauri::command]
+pub fn get_device_settings(
+    device_key: String,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> DeviceSettings {
+    settings.get(&device_key)
+}
+
+#[tauri::command]
+pub fn list_device_settings(settings: State<'_, Arc<DeviceSettingsStore>>) -> HashMap<String, DeviceSettings> {
+    settings.all()
+}
+
+/// Save overrides for a device; all-default settings remove its entry
+#[tauri::command]
+pub fn set_device_settings(
+    device_key: String,
+    device_settings: DeviceSettings,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<(), String> {
+    settings.set(&device_key, device_settings)
+}
+
+/// Turn protocol tracing on or off; returns the folder traces are written to.
+/// Traces of user or template downloads contain user passwords and card numbers
+#[tauri::command]
+pub fn set_protocol_trace(enabled: bool, app: AppHandle) -> Result<Option<String>, String> {
+    let dir = if enabled {
+        let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        Some(data_dir.join("traces"))
+    } else {
+        None
+    };
+    zk_trace::set_trace_dir(dir.clone())?;
+    Ok(dir.map(|d| d.display().to_string()))
+}
+
+/// Re-run an attendance download from a saved protocol trace
+#[tauri::command]
+pub async fn replay_protocol_trace(
+    path: String,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<AttendanceResponse, ZkError> {
+    zkteco_client::replay_attendance_trace(&path, settings.inner().clone()).await
+}
@@ -0,0 +1,110 @@
+//! User, template and device archive commands
+
+use std::sync::Arc;
+use tauri::State;
+use crate::zkteco_client::{
+    ArchiveRestorePlan, DeviceArchiveSummary, DeviceTarget, DeviceUserList, RosterSyncResult,
+    TemplateTransferSummary, UserInput, UserWriteResult,
+};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use crate::zkteco_client;
+
+/// Users enrolled on a device, for enrolment audits
+#[tauri::command]
+pub async fn list_device_users(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<DeviceUserList, ZkError> {
+    zkteco_client::list_device_users(&ip, port, password, settings.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn set_device_user(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    user: UserInput,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<UserWriteResult, ZkError> {
+    zkteco_client::set_device_user(&ip, port, password, user, settings.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn delete_device_user(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    uid: Option<u16>,
+    user_id: Option<String>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<u16, ZkError> {
+    zkteco_client::delete_device_user(&ip, port, password, uid, user_id, settings.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn backup_device_templates(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<TemplateTransferSummary, ZkError> {
+    zkteco_client::backup_templates(&ip, port, password, path, settings.inner().clone()).await
+}
+
+#[tauri::command]
+pub async fn restore_device_templates(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<TemplateTransferSummary, ZkError> {
+    zkteco_client::restore_templates(&ip, port, password, path, settings.inner().clone()).await
+}
+
+/// Save a device's info, options, users, templates and attendance log to one archive file
+#[tauri::command]
+pub async fn backup_device(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<DeviceArchiveSummary, ZkError> {
+    zkteco_client::backup_device(&ip, port, password, path, settings.inner().clone()).await
+}
+
+/// Push the users and templates of `source` to every target; with `dry_run` only the
+/// adds, updates and deletes each target would get are returned. Deleting users
+/// missing from the source is refused unless `confirm` is true.
+#[tauri::command]
+pub async fn sync_user_roster(
+    source: DeviceTarget,
+    targets: Vec<DeviceTarget>,
+    delete_missing: Option<bool>,
+    dry_run: bool,
+    confirm: Option<bool>,
+    max_concurrent: Option<usize>,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<RosterSyncResult, ZkError> {
+    let delete_missing = delete_missing.unwrap_or(false);
+    let max_concurrent = max_concurrent.unwrap_or(zkteco_client::FLEET_DEFAULT_CONCURRENCY);
+    zkteco_client::sync_user_roster(source, targets, delete_missing, dry_run, confirm.unwrap_or(false), max_concurrent, settings.inner().clone()).await
+}
+
+/// Restore an archive onto a device; with `dry_run` only the planned changes are returned
+#[tauri::command]
+pub async fn restore_device(
+    ip: String,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    dry_run: bool,
+    settings: State<'_, Arc<DeviceSettingsStore>>,
+) -> Result<ArchiveRestorePlan, ZkError> {
+    zkteco_client::restore_device(&ip, port, password, path, dry_run, settings.inner().clone()).await
+}
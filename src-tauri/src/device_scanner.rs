@@ -5,16 +5,21 @@ use tokio::net::TcpStream;
 use tokio::sync::Semaphore;
 use std::sync::Arc;
 use log::{info, warn};
-use crate::zkteco_client::get_device_info_quick;
+use crate::zk_error::ZkError;
+use crate::zkteco_client::{get_device_info_quick, probe_udp, DeviceCapacity};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BiometricDevice {
     pub ip: String,
     pub mac: String,
     pub open_ports: Vec<u16>,
+    pub protocol: String,  // "tcp" or "udp" (legacy terminals)
     pub device_name: Option<String>,
     pub firmware_version: Option<String>,
     pub serial_number: Option<String>,
+    pub capacity: Option<DeviceCapacity>,
+    pub password_required: bool,     // Device rejected the scan's COMM password (none by default)
+    pub info_error: Option<String>,  // Why name/serial/capacity are missing, if they are
 }
 
 // Common ports for biometric/time-attendance devices
@@ -23,9 +28,34 @@ const ZKTECO_PORTS: &[u16] = &[4370, 4360, 5005, 5010, 89];
 // Web/service ports
 const OTHER_PORTS: &[u16] = &[80, 8080, 443, 8443];
 
+// Port probed over UDP when no ZKTeco TCP port answers
+const ZKTECO_UDP_PORT: u16 = 4370;
+
 // Max concurrent connections for scanning
 const MAX_CONCURRENT: usize = 100;
 
+/// What a scan probes. `Default` is the LAN-wide scan the app runs.
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    pub hosts: Option<Vec<String>>,  // None = every host of the local and common subnets
+    pub zkteco_ports: Vec<u16>,
+    pub other_ports: Vec<u16>,
+    pub udp_port: u16,
+    pub password: Option<u32>,       // COMM password used to read device info
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        ScanConfig {
+            hosts: None,
+            zkteco_ports: ZKTECO_PORTS.to_vec(),
+            other_ports: OTHER_PORTS.to_vec(),
+            udp_port: ZKTECO_UDP_PORT,
+            password: None,
+        }
+    }
+}
+
 fn get_local_ip() -> Result<Ipv4Addr, String> {
     let socket = std::net::UdpSocket::bind("0.0.0.0:0")
         .map_err(|e| format!("Failed to bind socket: {}", e))?;
@@ -58,18 +88,19 @@ async fn check_port(ip: &str, port: u16, timeout_ms: u64) -> bool {
 }
 
 /// Check if IP has biometric port open (fast check)
-async fn check_biometric_ip(ip: String, semaphore: Arc<Semaphore>) -> Option<BiometricDevice> {
+async fn check_biometric_ip(ip: String, semaphore: Arc<Semaphore>, config: Arc<ScanConfig>) -> Option<BiometricDevice> {
     // Only hold semaphore during port checking
     let main_port: Option<u16>;
     let mut open_ports: Vec<u16>;
+    let protocol: &str;
     
     {
         let _permit = semaphore.acquire().await.ok()?;
         
         // Check all ZKTeco ports to find the main one
-        main_port = {
+        let tcp_port = {
             let mut found = None;
-            for port in ZKTECO_PORTS {
+            for port in &config.zkteco_ports {
                 if check_port(&ip, *port, 500).await {
                     found = Some(*port);
                     break;
@@ -78,22 +109,29 @@ async fn check_biometric_ip(ip: String, semaphore: Arc<Semaphore>) -> Option<Bio
             found
         };
         
-        if main_port.is_none() {
-            return None;
-        }
-        
-        let port = main_port.unwrap();
-        open_ports = vec![port];
-        
-        // Check all other ZKTeco ports
-        for p in ZKTECO_PORTS {
-            if *p != port && check_port(&ip, *p, 300).await {
-                open_ports.push(*p);
+        if let Some(port) = tcp_port {
+            main_port = Some(port);
+            protocol = "tcp";
+            open_ports = vec![port];
+            
+            // Check all other ZKTeco ports
+            for p in &config.zkteco_ports {
+                if *p != port && check_port(&ip, *p, 300).await {
+                    open_ports.push(*p);
+                }
+            }
+        } else {
+            // Older terminals only answer UDP
+            if !probe_udp(&ip, config.udp_port, 500).await {
+                return None;
             }
+            main_port = Some(config.udp_port);
+            protocol = "udp";
+            open_ports = vec![config.udp_port];
         }
         
         // Check web/service ports
-        for p in OTHER_PORTS {
+        for p in &config.other_ports {
             if check_port(&ip, *p, 300).await {
                 open_ports.push(*p);
             }
@@ -108,15 +146,22 @@ async fn check_biometric_ip(ip: String, semaphore: Arc<Semaphore>) -> Option<Bio
     // Fetch device info (without holding semaphore - gives device time to respond)
     let port = main_port.unwrap();
     info!("🔍 Device found at {}, fetching info on port {}...", ip, port);
-    let device_info = get_device_info_quick(&ip, port).await;
+    let result = get_device_info_quick(&ip, port, config.password).await;
+    let password_required = matches!(result, Err(ZkError::AuthFailed(_)));
+    let info_error = result.as_ref().err().map(|e| e.to_string());
+    let device_info = result.ok();
     
     Some(BiometricDevice {
         ip,
         mac: "Unknown".to_string(),
         open_ports,
+        protocol: protocol.to_string(),
         device_name: device_info.as_ref().map(|d| d.device_name.clone()).filter(|s| !s.is_empty()),
         firmware_version: device_info.as_ref().map(|d| d.firmware_version.clone()).filter(|s| !s.is_empty()),
         serial_number: device_info.as_ref().map(|d| d.serial_number.clone()).filter(|s| !s.is_empty()),
+        capacity: device_info.and_then(|d| d.capacity),
+        password_required,
+        info_error,
     })
 }
 
@@ -130,7 +175,8 @@ const COMMON_SUBNETS: &[(u8, u8, u8)] = &[
     (172, 16, 0),
 ];
 
-pub async fn scan_network() -> Result<Vec<BiometricDevice>, String> {
+/// Every host of the local subnet and the common subnets
+fn subnet_hosts() -> Result<Vec<String>, String> {
     let local_ip = get_local_ip()?;
     let local_parts: Vec<u8> = local_ip.octets().to_vec();
     
@@ -148,22 +194,41 @@ pub async fn scan_network() -> Result<Vec<BiometricDevice>, String> {
     
     info!("🔍 Scanning {} subnets: local + common", subnets_to_scan.len());
     
+    let mut hosts = Vec::new();
+    for (a, b, c) in &subnets_to_scan {
+        for i in 1..255u8 {
+            hosts.push(format!("{}.{}.{}.{}", a, b, c, i));
+        }
+    }
+    Ok(hosts)
+}
+
+/// Scan the LAN; `password` is the COMM password tried when reading device info
+pub async fn scan_network(password: Option<u32>) -> Result<Vec<BiometricDevice>, String> {
+    scan_network_with(ScanConfig { password, ..ScanConfig::default() }).await
+}
+
+pub async fn scan_network_with(config: ScanConfig) -> Result<Vec<BiometricDevice>, String> {
+    let hosts = match &config.hosts {
+        Some(hosts) => hosts.clone(),
+        None => subnet_hosts()?,
+    };
+    let config = Arc::new(config);
+    
     // Create semaphore for concurrent connections
     let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT));
     
-    // Spawn tasks for all IPs in all subnets
+    // Spawn tasks for all IPs
     let mut handles = Vec::new();
     
-    for (a, b, c) in &subnets_to_scan {
-        for i in 1..255u8 {
-            let ip = format!("{}.{}.{}.{}", a, b, c, i);
-            let sem = Arc::clone(&semaphore);
-            
-            let handle = tokio::spawn(async move {
-                check_biometric_ip(ip, sem).await
-            });
-            handles.push(handle);
-        }
+    for ip in hosts {
+        let sem = Arc::clone(&semaphore);
+        let config = Arc::clone(&config);
+        
+        let handle = tokio::spawn(async move {
+            check_biometric_ip(ip, sem, config).await
+        });
+        handles.push(handle);
     }
     
     info!("🔍 Checking {} IPs...", handles.len());
@@ -186,3 +251,68 @@ pub async fn scan_network() -> Result<Vec<BiometricDevice>, String> {
     
     Ok(biometric_devices)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk_simulator::{SimConfig, Simulator};
+    
+    fn localhost(port: u16) -> ScanConfig {
+        ScanConfig {
+            hosts: Some(vec!["127.0.0.1".to_string()]),
+            zkteco_ports: vec![port],
+            other_ports: Vec::new(),
+            udp_port: port,
+            password: None,
+        }
+    }
+    
+    #[tokio::test]
+    async fn finds_simulated_device() {
+        let sim = Simulator::start(SimConfig::default()).await;
+        let devices = scan_network_with(localhost(sim.port)).await.unwrap();
+        
+        assert_eq!(devices.len(), 1);
+        let device = &devices[0];
+        assert_eq!(device.ip, "127.0.0.1");
+        assert_eq!(device.protocol, "tcp");
+        assert_eq!(device.open_ports, vec![sim.port]);
+        assert_eq!(device.device_name.as_deref(), Some("SIM-F18"));
+        assert_eq!(device.serial_number.as_deref(), Some("SIM0000000001"));
+        assert!(device.capacity.is_some());
+        assert!(!device.password_required);
+    }
+    
+    #[tokio::test]
+    async fn reports_password_protected_device() {
+        let sim = Simulator::start(SimConfig { password: 777, ..SimConfig::default() }).await;
+        let devices = scan_network_with(localhost(sim.port)).await.unwrap();
+        assert!(devices[0].password_required);
+        assert!(devices[0].serial_number.is_none());
+        assert!(devices[0].info_error.is_some());
+        
+        let config = ScanConfig { password: Some(777), ..localhost(sim.port) };
+        let devices = scan_network_with(config).await.unwrap();
+        assert!(!devices[0].password_required);
+        assert_eq!(devices[0].serial_number.as_deref(), Some("SIM0000000001"));
+    }
+    
+    #[tokio::test]
+    async fn finds_udp_only_device() {
+        let sim = Simulator::start_udp(SimConfig::default()).await;
+        let devices = scan_network_with(localhost(sim.port)).await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].protocol, "udp");
+        assert_eq!(devices[0].serial_number.as_deref(), Some("SIM0000000001"));
+    }
+    
+    #[tokio::test]
+    async fn empty_host_is_skipped() {
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let devices = scan_network_with(localhost(port)).await.unwrap();
+        assert!(devices.is_empty());
+    }
+}
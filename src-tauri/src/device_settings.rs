@@ -0,0 +1,119 @@
+//! Per-device settings (layout overrides etc.), persisted as JSON in the app data directory
+
+use chrono_tz::Tz;
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use log::{info, warn};
+
+pub const RECORD_SIZES: &[usize] = &[8, 16, 40];
+pub const USER_SIZES: &[usize] = &[28, 72];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSettings {
+    #[serde(default)]
+    pub record_size: Option<usize>,     // Attendance record layout: 8, 16 or 40 (None = detect)
+    #[serde(default)]
+    pub user_size: Option<usize>,       // User record layout: 28 or 72 (None = detect)
+    #[serde(default)]
+    pub timezone: Option<String>,       // IANA zone the device clock runs in, e.g. "Europe/Berlin" (None = this PC's zone)
+    #[serde(default)]
+    pub name_encoding: Option<String>,  // Code page of user names, e.g. "gbk", "windows-1252" (None = detect)
+    #[serde(default)]
+    pub push_allowed: bool,             // Accept ADMS uploads under this serial number
+}
+
+impl DeviceSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(size) = self.record_size {
+            if !RECORD_SIZES.contains(&size) {
+                return Err(format!("Record layout must be one of {:?} bytes, got {}", RECORD_SIZES, size));
+            }
+        }
+        if let Some(size) = self.user_size {
+            if !USER_SIZES.contains(&size) {
+                return Err(format!("User layout must be one of {:?} bytes, got {}", USER_SIZES, size));
+            }
+        }
+        if let Some(name) = &self.timezone {
+            if name.parse::<Tz>().is_err() {
+                return Err(format!("Unknown timezone '{}'", name));
+            }
+        }
+        if let Some(label) = &self.name_encoding {
+            if Encoding::for_label(label.as_bytes()).is_none() {
+                return Err(format!("Unknown name encoding '{}'", label));
+            }
+        }
+        Ok(())
+    }
+
+    /// The configured user name encoding, if one is set
+    pub fn name_encoding(&self) -> Option<&'static Encoding> {
+        self.name_encoding.as_ref().and_then(|label| Encoding::for_label(label.as_bytes()))
+    }
+
+    /// The device's timezone, if one is set
+    pub fn timezone(&self) -> Option<Tz> {
+        self.timezone.as_ref().and_then(|name| name.parse().ok())
+    }
+}
+
+pub struct DeviceSettingsStore {
+    path: PathBuf,
+    settings: Mutex<HashMap<String, DeviceSettings>>,
+}
+
+impl DeviceSettingsStore {
+    /// Load settings from `path` (a missing or unreadable file starts empty)
+    pub fn load(path: PathBuf) -> Self {
+        let settings = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt device settings file {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        info!("Loaded settings for {} device(s)", settings.len());
+
+        DeviceSettingsStore {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    /// Settings for a device (defaults when none are stored)
+    pub fn get(&self, device_key: &str) -> DeviceSettings {
+        self.settings.lock().ok()
+            .and_then(|s| s.get(device_key).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn all(&self) -> HashMap<String, DeviceSettings> {
+        self.settings.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub fn set(&self, device_key: &str, device_settings: DeviceSettings) -> Result<(), String> {
+        device_settings.validate()?;
+        let mut settings = self.settings.lock().map_err(|e| format!("Settings lock poisoned: {}", e))?;
+        if device_settings == DeviceSettings::default() {
+            settings.remove(device_key);
+        } else {
+            settings.insert(device_key.to_string(), device_settings);
+        }
+        self.save(&settings)
+    }
+
+    fn save(&self, settings: &HashMap<String, DeviceSettings>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Failed to serialize device settings: {}", e))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
@@ -5,210 +5,22 @@ mod media_converter;
 mod document_converter;
 mod bundled_converter;
 mod ai_assistant;
-
-use device_scanner::{scan_network, BiometricDevice};
-use zkteco_client::{connect_and_fetch_attendance, AttendanceResponse};
-use media_converter::{
-    VideoConvertOptions, ImageConvertOptions, ConversionResult, MediaInfo,
-};
-use document_converter::ToolStatus;
-use ai_assistant::{AIProvider, ChatRequest, ChatResponse};
-
-// ============================================================================
-// Attendance Commands
-// ============================================================================
-
-#[tauri::command]
-async fn scan_for_devices() -> Result<Vec<BiometricDevice>, String> {
-    scan_network().await
-}
-
-#[tauri::command]
-async fn fetch_attendance(ip: String, port: u16) -> Result<AttendanceResponse, String> {
-    connect_and_fetch_attendance(&ip, port).await
-}
-
-// ============================================================================
-// Media Commands - FFmpeg
-// ============================================================================
-
-#[tauri::command]
-fn check_ffmpeg_status() -> Result<String, String> {
-    media_converter::check_ffmpeg()
-}
-
-#[tauri::command]
-async fn get_media_information(file_path: String) -> Result<MediaInfo, String> {
-    media_converter::get_media_info(&file_path).await
-}
-
-// ============================================================================
-// Video Commands
-// ============================================================================
-
-#[tauri::command]
-async fn video_convert(options: VideoConvertOptions) -> Result<ConversionResult, String> {
-    media_converter::convert_video(options).await
-}
-
-#[tauri::command]
-async fn video_compress(
-    input_path: String,
-    output_path: String,
-    target_bitrate: Option<String>,
-) -> Result<ConversionResult, String> {
-    media_converter::compress_video(input_path, output_path, target_bitrate).await
-}
-
-#[tauri::command]
-async fn video_extract_audio(
-    input_path: String,
-    output_path: String,
-    format: String,
-) -> Result<ConversionResult, String> {
-    media_converter::extract_audio(input_path, output_path, format).await
-}
-
-// ============================================================================
-// Image Commands
-// ============================================================================
-
-#[tauri::command]
-async fn image_convert(options: ImageConvertOptions) -> Result<ConversionResult, String> {
-    media_converter::convert_image(options).await
-}
-
-#[tauri::command]
-async fn image_compress(
-    input_path: String,
-    output_path: String,
-    quality: u32,
-) -> Result<ConversionResult, String> {
-    media_converter::compress_image(input_path, output_path, quality).await
-}
-
-#[tauri::command]
-async fn image_resize(
-    input_path: String,
-    output_path: String,
-    width: u32,
-    height: u32,
-    maintain_aspect: bool,
-) -> Result<ConversionResult, String> {
-    media_converter::resize_image(input_path, output_path, width, height, maintain_aspect).await
-}
-
-// ============================================================================
-// Document Commands (External tools - optional)
-// ============================================================================
-
-#[tauri::command]
-fn check_document_tools() -> Vec<ToolStatus> {
-    document_converter::check_tools()
-}
-
-#[tauri::command]
-async fn document_convert_office(
-    input_path: String,
-    output_format: String,
-    output_dir: String,
-) -> Result<document_converter::ConversionResult, String> {
-    document_converter::convert_with_libreoffice(input_path, output_format, output_dir).await
-}
-
-#[tauri::command]
-async fn document_convert_pandoc(
-    input_path: String,
-    output_path: String,
-    from_format: Option<String>,
-    to_format: Option<String>,
-) -> Result<document_converter::ConversionResult, String> {
-    document_converter::convert_with_pandoc(input_path, output_path, from_format, to_format).await
-}
-
-// ============================================================================
-// Bundled Document Commands (No external dependencies!)
-// ============================================================================
-
-#[tauri::command]
-fn bundled_get_doc_info(file_path: String) -> Result<bundled_converter::DocumentInfo, String> {
-    bundled_converter::get_document_info(&file_path)
-}
-
-#[tauri::command]
-fn bundled_merge_pdfs(
-    input_paths: Vec<String>,
-    output_path: String,
-) -> Result<bundled_converter::ConversionResult, String> {
-    bundled_converter::merge_pdfs(input_paths, output_path)
-}
-
-#[tauri::command]
-fn bundled_excel_to_csv(
-    input_path: String,
-    output_path: String,
-    sheet_index: Option<usize>,
-) -> Result<bundled_converter::ConversionResult, String> {
-    bundled_converter::excel_to_csv(input_path, output_path, sheet_index)
-}
-
-#[tauri::command]
-fn bundled_csv_to_json(
-    input_path: String,
-    output_path: String,
-) -> Result<bundled_converter::ConversionResult, String> {
-    bundled_converter::csv_to_json(input_path, output_path)
-}
-
-#[tauri::command]
-fn bundled_json_to_csv(
-    input_path: String,
-    output_path: String,
-) -> Result<bundled_converter::ConversionResult, String> {
-    bundled_converter::json_to_csv(input_path, output_path)
-}
-
-#[tauri::command]
-fn bundled_convert_image(
-    input_path: String,
-    output_path: String,
-    quality: Option<u8>,
-) -> Result<bundled_converter::ConversionResult, String> {
-    bundled_converter::convert_image_format(input_path, output_path, quality)
-}
-
-#[tauri::command]
-fn bundled_resize_image(
-    input_path: String,
-    output_path: String,
-    width: u32,
-    height: u32,
-    maintain_aspect: bool,
-) -> Result<bundled_converter::ConversionResult, String> {
-    bundled_converter::resize_image(input_path, output_path, width, height, maintain_aspect)
-}
-
-// ============================================================================
-// AI Assistant Commands
-// ============================================================================
-
-#[tauri::command]
-fn ai_get_providers() -> Vec<AIProvider> {
-    ai_assistant::get_providers()
-}
-
-#[tauri::command]
-async fn ai_chat(
-    request: ChatRequest,
-    api_key: Option<String>,
-) -> Result<ChatResponse, String> {
-    ai_assistant::chat(request, api_key).await
-}
-
-#[tauri::command]
-fn ai_get_system_prompt() -> String {
-    ai_assistant::get_system_prompt()
-}
+mod watermark_store;
+mod zk_error;
+mod zk_trace;
+#[cfg(test)]
+mod zk_simulator;
+mod attendance_store;
+mod device_settings;
+mod adms_server;
+mod commands;
+
+use std::sync::Arc;
+use tauri::Manager;
+use watermark_store::WatermarkStore;
+use device_settings::DeviceSettingsStore;
+use attendance_store::AttendanceStore;
+use commands::{attendance::*, live::*, users::*, device::*, settings::*, media::*, documents::*, ai::*};
 
 // ============================================================================
 // App Entry Point
@@ -226,10 +38,56 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(Arc::new(WatermarkStore::load(data_dir.join("attendance_watermarks.json"))));
+            app.manage(AttendanceStore::open(&data_dir.join("attendance.db"))?);
+            app.manage(LiveCaptures::default());
+            app.manage(AdmsServers::default());
+            app.manage(Arc::new(DeviceSettingsStore::load(data_dir.join("device_settings.json"))));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Attendance
             scan_for_devices,
             fetch_attendance,
+            fetch_attendance_since,
+            fetch_attendance_fleet,
+            reset_attendance_watermark,
+            query_attendance,
+            list_stored_devices,
+            start_live_capture,
+            stop_live_capture,
+            start_adms_server,
+            stop_adms_server,
+            list_adms_devices,
+            queue_adms_command,
+            list_device_users,
+            set_device_user,
+            delete_device_user,
+            backup_device_templates,
+            restore_device_templates,
+            backup_device,
+            restore_device,
+            sync_user_roster,
+            get_device_time,
+            set_device_time,
+            sync_device_clocks,
+            fetch_and_clear_attendance,
+            get_device_capacity,
+            restart_device,
+            power_off_device,
+            unlock_door,
+            play_test_voice,
+            refresh_device_data,
+            get_device_options,
+            set_device_options,
+            dump_device_options,
+            get_device_settings,
+            list_device_settings,
+            set_device_settings,
+            set_protocol_trace,
+            replay_protocol_trace,
             // Media (FFmpeg)
             check_ffmpeg_status,
             get_media_information,
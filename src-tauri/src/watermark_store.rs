@@ -0,0 +1,59 @@
+//! Per-device attendance watermarks, persisted as JSON in the app data directory
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use log::{info, warn};
+use crate::zkteco_client::AttendanceWatermark;
+
+pub struct WatermarkStore {
+    path: PathBuf,
+    marks: Mutex<HashMap<String, AttendanceWatermark>>,
+}
+
+impl WatermarkStore {
+    /// Load watermarks from `path` (a missing or unreadable file starts empty)
+    pub fn load(path: PathBuf) -> Self {
+        let marks = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt watermark file {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        info!("Loaded {} attendance watermark(s)", marks.len());
+
+        WatermarkStore {
+            path,
+            marks: Mutex::new(marks),
+        }
+    }
+
+    pub fn get(&self, device_key: &str) -> Option<AttendanceWatermark> {
+        self.marks.lock().ok()?.get(device_key).cloned()
+    }
+
+    pub fn set(&self, device_key: &str, mark: AttendanceWatermark) -> Result<(), String> {
+        let mut marks = self.marks.lock().map_err(|e| format!("Watermark lock poisoned: {}", e))?;
+        marks.insert(device_key.to_string(), mark);
+        self.save(&marks)
+    }
+
+    /// Forget a device's watermark so the next fetch downloads the whole log
+    pub fn clear(&self, device_key: &str) -> Result<(), String> {
+        let mut marks = self.marks.lock().map_err(|e| format!("Watermark lock poisoned: {}", e))?;
+        marks.remove(device_key);
+        self.save(&marks)
+    }
+
+    fn save(&self, marks: &HashMap<String, AttendanceWatermark>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(marks)
+            .map_err(|e| format!("Failed to serialize watermarks: {}", e))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+}
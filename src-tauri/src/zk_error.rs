@@ -0,0 +1,229 @@
+//! Errors from the ZKTeco protocol layer, serialized to the frontend as
+//! `{ code, message, retryable, retry_hint }` so the UI can react to the kind of failure
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZkError {
+    /// No TCP connection / handshake answer within the connect timeout
+    ConnectTimeout(String),
+    /// Host answered but nothing listens on the port
+    ConnectionRefused(String),
+    /// Device rejected the communication password
+    AuthFailed(String),
+    /// A read timed out on an established session
+    Timeout(String),
+    /// The device reset or closed an established connection
+    ConnectionLost(String),
+    /// TCP frame did not start with the 0x5050/0x7D82 magic
+    BadMagic(String),
+    /// Packet shorter than its header or declared size
+    ShortPacket(String),
+    /// Device answered with a command we did not expect
+    UnexpectedCommand { context: String, cmd: u16 },
+    /// Device is busy (another session, menu open, or asked us to retry)
+    DeviceBusy(String),
+    /// No room left on the device for what was written
+    DeviceFull(String),
+    /// Other socket-level failure
+    Io(String),
+    /// Data received but it could not be understood
+    Protocol(String),
+    /// The request itself is unusable (bad value, missing field, unconfirmed action)
+    InvalidArgument(String),
+    /// Anything outside the protocol (files, storage)
+    Other(String),
+}
+
+impl ZkError {
+    /// Build the error for a reply command that is not the one we wanted
+    pub fn reply(context: &str, cmd: u16) -> Self {
+        match cmd {
+            crate::zkteco_client::CMD_ACK_UNAUTH => ZkError::AuthFailed(format!("{}: device requires authentication", context)),
+            crate::zkteco_client::CMD_ACK_RETRY | crate::zkteco_client::CMD_ACK_REPEAT => {
+                ZkError::DeviceBusy(format!("{}: device asked to retry (cmd={})", context, cmd))
+            }
+            _ => ZkError::UnexpectedCommand { context: context.to_string(), cmd },
+        }
+    }
+
+    /// Classify a socket error by its kind
+    pub fn io(context: &str, e: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        let message = format!("{}: {}", context, e);
+        match e.kind() {
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => ZkError::Timeout(message),
+            ErrorKind::ConnectionRefused => ZkError::ConnectionRefused(message),
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => ZkError::ConnectionLost(message),
+            _ => ZkError::Io(message),
+        }
+    }
+
+    /// Rebuild an error from its `code()`, e.g. one saved in a protocol trace
+    pub fn from_code(code: &str, message: String) -> Self {
+        match code {
+            "connect_timeout" => ZkError::ConnectTimeout(message),
+            "connection_refused" => ZkError::ConnectionRefused(message),
+            "auth_failed" => ZkError::AuthFailed(message),
+            "timeout" => ZkError::Timeout(message),
+            "connection_lost" => ZkError::ConnectionLost(message),
+            "bad_magic" => ZkError::BadMagic(message),
+            "short_packet" => ZkError::ShortPacket(message),
+            "device_busy" => ZkError::DeviceBusy(message),
+            "device_full" => ZkError::DeviceFull(message),
+            "io" => ZkError::Io(message),
+            "unexpected_command" | "protocol" => ZkError::Protocol(message),
+            "invalid_argument" => ZkError::InvalidArgument(message),
+            _ => ZkError::Other(message),
+        }
+    }
+
+    /// A timeout while still connecting means the device never answered
+    pub fn during_connect(self) -> Self {
+        match self {
+            ZkError::Timeout(m) => ZkError::ConnectTimeout(m),
+            other => other,
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, ZkError::Timeout(_) | ZkError::ConnectTimeout(_))
+    }
+
+    /// Same kind of error with a different message
+    pub fn with_message(self, message: String) -> Self {
+        match self {
+            ZkError::ConnectTimeout(_) => ZkError::ConnectTimeout(message),
+            ZkError::ConnectionRefused(_) => ZkError::ConnectionRefused(message),
+            ZkError::AuthFailed(_) => ZkError::AuthFailed(message),
+            ZkError::Timeout(_) => ZkError::Timeout(message),
+            ZkError::ConnectionLost(_) => ZkError::ConnectionLost(message),
+            ZkError::BadMagic(_) => ZkError::BadMagic(message),
+            ZkError::ShortPacket(_) => ZkError::ShortPacket(message),
+            ZkError::UnexpectedCommand { .. } => ZkError::Protocol(message),
+            ZkError::DeviceBusy(_) => ZkError::DeviceBusy(message),
+            ZkError::DeviceFull(_) => ZkError::DeviceFull(message),
+            ZkError::Io(_) => ZkError::Io(message),
+            ZkError::Protocol(_) => ZkError::Protocol(message),
+            ZkError::InvalidArgument(_) => ZkError::InvalidArgument(message),
+            ZkError::Other(_) => ZkError::Other(message),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ZkError::ConnectTimeout(_) => "connect_timeout",
+            ZkError::ConnectionRefused(_) => "connection_refused",
+            ZkError::AuthFailed(_) => "auth_failed",
+            ZkError::Timeout(_) => "timeout",
+            ZkError::ConnectionLost(_) => "connection_lost",
+            ZkError::BadMagic(_) => "bad_magic",
+            ZkError::ShortPacket(_) => "short_packet",
+            ZkError::UnexpectedCommand { .. } => "unexpected_command",
+            ZkError::DeviceBusy(_) => "device_busy",
+            ZkError::DeviceFull(_) => "device_full",
+            ZkError::Io(_) => "io",
+            ZkError::Protocol(_) => "protocol",
+            ZkError::InvalidArgument(_) => "invalid_argument",
+            ZkError::Other(_) => "other",
+        }
+    }
+
+    /// Whether trying the same operation again can succeed without user action
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            ZkError::ConnectTimeout(_)
+                | ZkError::Timeout(_)
+                | ZkError::ConnectionLost(_)
+                | ZkError::ShortPacket(_)
+                | ZkError::DeviceBusy(_)
+                | ZkError::Io(_)
+        )
+    }
+
+    pub fn retry_hint(&self) -> &'static str {
+        match self {
+            ZkError::ConnectTimeout(_) => "Check the device is powered on and reachable from this PC, then retry",
+            ZkError::ConnectionRefused(_) => "Check the port (usually 4370) in the device's Comm settings",
+            ZkError::AuthFailed(_) => "Enter the device's communication key (COMM password); retrying as-is will fail again",
+            ZkError::Timeout(_) => "The device stopped answering mid-transfer; retry, or use a wired connection",
+            ZkError::ConnectionLost(_) => "The device dropped the connection; retry, and check it is not restarting",
+            ZkError::BadMagic(_) => "This port is not speaking the ZKTeco protocol; check the IP and port",
+            ZkError::ShortPacket(_) => "A packet was cut off; retry, the network may be dropping data",
+            ZkError::UnexpectedCommand { .. } => "The device does not support this operation or this firmware differs; retrying will not help",
+            ZkError::DeviceBusy(_) => "Close other programs or the device menu using the terminal, wait a few seconds and retry",
+            ZkError::DeviceFull(_) => "Delete users that are no longer needed from the device, then retry",
+            ZkError::Io(_) => "Network error; retry",
+            ZkError::Protocol(_) => "The device sent data in an unexpected format; retrying will not help",
+            ZkError::InvalidArgument(_) => "Correct the values entered; retrying as-is will fail again",
+            ZkError::Other(_) => "",
+        }
+    }
+}
+
+impl fmt::Display for ZkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZkError::UnexpectedCommand { context, cmd } => write!(f, "{}: cmd={}", context, cmd),
+            ZkError::ConnectTimeout(m)
+            | ZkError::ConnectionRefused(m)
+            | ZkError::AuthFailed(m)
+            | ZkError::Timeout(m)
+            | ZkError::ConnectionLost(m)
+            | ZkError::BadMagic(m)
+            | ZkError::ShortPacket(m)
+            | ZkError::DeviceBusy(m)
+            | ZkError::DeviceFull(m)
+            | ZkError::Io(m)
+            | ZkError::Protocol(m)
+            | ZkError::InvalidArgument(m)
+            | ZkError::Other(m) => f.write_str(m),
+        }
+    }
+}
+
+impl std::error::Error for ZkError {}
+
+impl From<ZkError> for String {
+    fn from(e: ZkError) -> Self {
+        e.to_string()
+    }
+}
+
+impl Serialize for ZkError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ZkError", 4)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.serialize_field("retryable", &self.retryable())?;
+        s.serialize_field("retry_hint", self.retry_hint())?;
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind};
+
+    #[test]
+    fn classifies_socket_errors() {
+        let kind = |k: ErrorKind| ZkError::io("Read failed", Error::from(k)).code();
+        assert_eq!(kind(ErrorKind::TimedOut), "timeout");
+        assert_eq!(kind(ErrorKind::ConnectionRefused), "connection_refused");
+        assert_eq!(kind(ErrorKind::ConnectionReset), "connection_lost");
+        assert_eq!(kind(ErrorKind::ConnectionAborted), "connection_lost");
+        assert_eq!(kind(ErrorKind::BrokenPipe), "io");
+        assert!(ZkError::io("Read failed", Error::from(ErrorKind::ConnectionReset)).retryable());
+    }
+
+    #[test]
+    fn invalid_arguments_are_not_retryable() {
+        let e = ZkError::InvalidArgument("Invalid since timestamp".to_string());
+        assert_eq!(e.code(), "invalid_argument");
+        assert!(!e.retryable());
+        assert_eq!(serde_json::to_value(&e).unwrap()["message"], "Invalid since timestamp");
+    }
+}
@@ -0,0 +1,238 @@
+//! In-process ZKTeco terminal for tests. Listens on 127.0.0.1, speaks the TCP
+//! framing, checksum, session and commkey rules of real devices, and serves
+//! users and attendance in the 28/72-byte user and 8/16/40-byte record layouts.
+//! TCP sessions that register for EF_ATTLOG also get punches pushed as realtime events.
+//!
+//! Packet encoding is written independently of `zkteco_client` on purpose, so a
+//! bug there is not mirrored here.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use chrono::NaiveDateTime;
+use encoding_rs::{Encoding, UTF_8};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+mod session;
+mod handler;
+mod tables;
+mod packet;
+
+use session::*;
+use handler::*;
+use tables::*;
+use packet::*;
+
+#[derive(Debug, Clone)]
+pub struct SimUser {
+    pub uid: u16,
+    pub user_id: String,
+    pub name: String,
+    pub privilege: u8,
+    pub password: String,
+    pub card: u32,
+    pub group_id: String,
+    pub fingers: Vec<u8>,           // Enrolled finger indexes
+    pub face: bool,
+}
+
+impl SimUser {
+    pub fn new(uid: u16, user_id: &str, name: &str) -> Self {
+        SimUser {
+            uid,
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            privilege: 0,
+            password: String::new(),
+            card: 0,
+            group_id: "1".to_string(),
+            fingers: Vec::new(),
+            face: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimPunch {
+    pub uid: u16,
+    pub user_id: String,
+    pub time: NaiveDateTime,
+    pub status: u8,
+    pub punch: u8,
+    pub workcode: u32,
+}
+
+impl SimPunch {
+    pub fn new(user: &SimUser, time: NaiveDateTime, punch: u8) -> Self {
+        SimPunch {
+            uid: user.uid,
+            user_id: user.user_id.clone(),
+            time,
+            status: 1,
+            punch,
+            workcode: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    pub device_name: String,
+    pub serial_number: String,
+    pub firmware_version: String,
+    pub platform: String,
+    pub mac: String,
+    pub password: u32,              // COMM key, 0 = no authentication
+    pub user_size: usize,           // 28 or 72
+    pub record_size: usize,         // 8, 16 or 40
+    pub users: Vec<SimUser>,
+    pub punches: Vec<SimPunch>,
+    pub clock: Option<NaiveDateTime>,   // None = PC local time
+    pub clock_unreadable: bool,         // Answer CMD_GET_TIME with ACK_ERROR
+    pub reported_records: Option<u32>,  // Record count in CMD_GET_FREE_SIZES (None = actual)
+    pub sizes_unavailable: bool,        // Answer CMD_GET_FREE_SIZES with ACK_ERROR
+    pub name_encoding: &'static Encoding,   // Code page user names are stored in
+    pub face_capacity: u32,         // 0 = not a face terminal
+    pub face_reply: u16,            // Reply carrying a face template: CMD_ACK_OK, CMD_DATA or CMD_PREPARE_DATA (streamed)
+    pub user_capacity: u32,
+    pub controls: Vec<(u16, u32)>,  // Control commands received (command, argument)
+    pub disables: u32,              // CMD_DISABLEDEVICE requests received
+    pub options: HashMap<String, String>,   // Writable options besides the identity ones
+    pub locked_options: Vec<String>,        // Options whose writes are refused
+    pub templates: HashMap<(u16, u8), Vec<u8>>, // Uploaded finger templates by (uid, fid); others use template_bytes
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            device_name: "SIM-F18".to_string(),
+            serial_number: "SIM0000000001".to_string(),
+            firmware_version: "Ver 6.60 Sim".to_string(),
+            platform: "ZMM220_TFT".to_string(),
+            mac: "00:17:61:00:00:01".to_string(),
+            password: 0,
+            user_size: 72,
+            record_size: 40,
+            users: Vec::new(),
+            punches: Vec::new(),
+            clock: None,
+            clock_unreadable: false,
+            reported_records: None,
+            sizes_unavailable: false,
+            name_encoding: UTF_8,
+            face_capacity: 0,
+            face_reply: CMD_ACK_OK,
+            user_capacity: 3000,
+            controls: Vec::new(),
+            disables: 0,
+            options: HashMap::new(),
+            locked_options: Vec::new(),
+            templates: HashMap::new(),
+        }
+    }
+}
+
+/// A running simulator; stops listening when dropped
+pub struct Simulator {
+    pub port: u16,
+    state: Arc<Mutex<SimConfig>>,
+    events: broadcast::Sender<SimPunch>,
+    task: JoinHandle<()>,
+}
+
+impl Simulator {
+    pub async fn start(config: SimConfig) -> Simulator {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind simulator");
+        let port = listener.local_addr().expect("simulator address").port();
+        let state = Arc::new(Mutex::new(config));
+
+        let (events, _) = broadcast::channel(64);
+
+        let shared = Arc::clone(&state);
+        let pushed = events.clone();
+        let task = tokio::spawn(async move {
+            let mut next_session: u16 = 0x1234;
+            while let Ok((stream, _)) = listener.accept().await {
+                next_session = next_session.wrapping_add(1).max(1);
+                tokio::spawn(serve(stream, Arc::clone(&shared), pushed.subscribe(), next_session));
+            }
+        });
+
+        Simulator { port, state, events, task }
+    }
+
+    /// Serve bare UDP datagrams instead, like older terminals (no TCP listener on the port)
+    pub async fn start_udp(config: SimConfig) -> Simulator {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind simulator");
+        let port = socket.local_addr().expect("simulator address").port();
+        let state = Arc::new(Mutex::new(config));
+        let (events, _) = broadcast::channel(1);
+
+        let shared = Arc::clone(&state);
+        let task = tokio::spawn(async move {
+            let mut sessions: HashMap<SocketAddr, Session> = HashMap::new();
+            let mut next_session: u16 = 0x4321;
+            let mut buf = vec![0u8; 65536];
+            while let Ok((n, peer)) = socket.recv_from(&mut buf).await {
+                let packet = &buf[..n];
+                if n < 8 {
+                    continue;
+                }
+                let cmd = u16::from_le_bytes([packet[0], packet[1]]);
+                if cmd == CMD_CONNECT {
+                    next_session = next_session.wrapping_add(1).max(1);
+                    sessions.insert(peer, Session::new(next_session));
+                }
+                let session = sessions.entry(peer).or_insert_with(|| Session::new(0));
+                for reply in respond(session, &shared, packet) {
+                    if socket.send_to(&reply, peer).await.is_err() {
+                        break;
+                    }
+                }
+                if matches!(cmd, CMD_EXIT | CMD_RESTART | CMD_POWEROFF) {
+                    sessions.remove(&peer);
+                }
+            }
+        });
+
+        Simulator { port, state, events, task }
+    }
+
+    pub fn punch_count(&self) -> usize {
+        self.state.lock().unwrap().punches.len()
+    }
+
+    pub fn push_punch(&self, punch: SimPunch) {
+        self.state.lock().unwrap().punches.push(punch);
+    }
+
+    pub fn set_punches(&self, punches: Vec<SimPunch>) {
+        self.state.lock().unwrap().punches = punches;
+    }
+
+    /// Someone punches at the terminal: log it and push it to registered TCP sessions
+    pub fn punch_live(&self, punch: SimPunch) {
+        self.state.lock().unwrap().punches.push(punch.clone());
+        let _ = self.events.send(punch);
+    }
+
+    pub fn clock(&self) -> Option<NaiveDateTime> {
+        self.state.lock().unwrap().clock
+    }
+
+    pub fn controls(&self) -> Vec<(u16, u32)> {
+        self.state.lock().unwrap().controls.clone()
+    }
+
+    pub fn disables(&self) -> u32 {
+        self.state.lock().unwrap().disables
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,168 @@
+//! Replies to each protocol command
+
+use chrono::Local;
+use super::*;
+
+pub(super) fn handle(session: &mut Session, config: &mut SimConfig, packet: &[u8]) -> Vec<(u16, Vec<u8>)> {
+    let cmd = u16::from_le_bytes([packet[0], packet[1]]);
+    let session_id = u16::from_le_bytes([packet[4], packet[5]]);
+    let data = &packet[8..];
+    let ok = |data: Vec<u8>| vec![(CMD_ACK_OK, data)];
+
+    if cmd == CMD_CONNECT {
+        session.connected = true;
+        session.authenticated = config.password == 0;
+        return if session.authenticated { ok(Vec::new()) } else { vec![(CMD_ACK_UNAUTH, Vec::new())] };
+    }
+    if !session.connected || session_id != session.id {
+        return vec![(CMD_ACK_UNAUTH, Vec::new())];
+    }
+    if cmd == CMD_AUTH {
+        session.authenticated = data == make_commkey(config.password, session.id).as_slice();
+        return if session.authenticated { ok(Vec::new()) } else { vec![(CMD_ACK_UNAUTH, Vec::new())] };
+    }
+    if !session.authenticated {
+        return vec![(CMD_ACK_UNAUTH, Vec::new())];
+    }
+
+    match cmd {
+        // The client acknowledging a pushed event expects no answer
+        CMD_ACK_OK => Vec::new(),
+        CMD_REG_EVENT if data.len() >= 4 => {
+            session.event_flags = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+            ok(Vec::new())
+        }
+        CMD_DISABLEDEVICE => {
+            config.disables += 1;
+            ok(Vec::new())
+        }
+        CMD_EXIT | CMD_ENABLEDEVICE | CMD_REFRESHDATA | CMD_REFRESHOPTION => ok(Vec::new()),
+        CMD_FREE_DATA => {
+            session.prepared.clear();
+            session.uploaded.clear();
+            ok(Vec::new())
+        }
+        CMD_PREPARE_DATA => {
+            session.uploaded.clear();
+            ok(Vec::new())
+        }
+        CMD_DATA => {
+            session.uploaded.extend_from_slice(data);
+            ok(Vec::new())
+        }
+        CMD_SAVE_USERTEMPS => match save_user_templates(config, &session.uploaded) {
+            Some(()) => ok(Vec::new()),
+            None => vec![(CMD_ACK_ERROR, Vec::new())],
+        },
+        CMD_VERSION => ok(nul_terminated(&config.firmware_version)),
+        CMD_OPTIONS_RRQ => {
+            let name = String::from_utf8_lossy(data).trim_end_matches('\0').to_string();
+            let value = match name.as_str() {
+                "~DeviceName" => Some(&config.device_name),
+                "~SerialNumber" => Some(&config.serial_number),
+                "~Platform" => Some(&config.platform),
+                "MAC" => Some(&config.mac),
+                other => config.options.get(other),
+            };
+            match value {
+                Some(value) => ok(nul_terminated(&format!("{}={}", name, value))),
+                None => vec![(CMD_ACK_ERROR, Vec::new())],
+            }
+        }
+        CMD_OPTIONS_WRQ => {
+            let pair = String::from_utf8_lossy(data).trim_end_matches('\0').to_string();
+            match pair.split_once('=') {
+                Some((key, value)) if !key.starts_with('~') && !config.locked_options.iter().any(|k| k == key) => {
+                    config.options.insert(key.to_string(), value.to_string());
+                    ok(Vec::new())
+                }
+                _ => vec![(CMD_ACK_ERROR, Vec::new())],
+            }
+        }
+        CMD_GET_TIME if config.clock_unreadable => vec![(CMD_ACK_ERROR, Vec::new())],
+        CMD_GET_TIME => {
+            let now = config.clock.unwrap_or_else(|| Local::now().naive_local());
+            ok(encode_time(&now).to_le_bytes().to_vec())
+        }
+        CMD_SET_TIME if data.len() >= 4 => {
+            config.clock = Some(decode_time(u32::from_le_bytes([data[0], data[1], data[2], data[3]])));
+            ok(Vec::new())
+        }
+        CMD_GET_FREE_SIZES if config.sizes_unavailable => vec![(CMD_ACK_ERROR, Vec::new())],
+        CMD_GET_FREE_SIZES => ok(free_sizes(config)),
+        CMD_RESTART | CMD_POWEROFF | CMD_UNLOCK | CMD_TESTVOICE => {
+            let argument = data.get(..4).map(|a| u32::from_le_bytes([a[0], a[1], a[2], a[3]])).unwrap_or(0);
+            config.controls.push((cmd, argument));
+            ok(Vec::new())
+        }
+        CMD_USER_WRQ if data.len() == config.user_size => {
+            // Enrolment stays with the slot; the record carries none
+            let mut user = decode_user(config, data);
+            match config.users.iter_mut().find(|u| u.uid == user.uid) {
+                Some(slot) => {
+                    user.fingers = std::mem::take(&mut slot.fingers);
+                    user.face = slot.face;
+                    *slot = user;
+                }
+                None => config.users.push(user),
+            }
+            ok(Vec::new())
+        }
+        CMD_DELETE_USER if data.len() >= 2 => {
+            let uid = u16::from_le_bytes([data[0], data[1]]);
+            config.users.retain(|u| u.uid != uid);
+            ok(Vec::new())
+        }
+        CMD_CLEAR_ATTLOG => {
+            config.punches.clear();
+            ok(Vec::new())
+        }
+        CMD_DATA_WRRQ if data.len() >= 11 => {
+            let inner = u16::from_le_bytes([data[1], data[2]]);
+            let records = match inner {
+                CMD_USERTEMP_RRQ => Some(encode_users(config)),
+                CMD_ATTLOG_RRQ => Some(encode_punches(config)),
+                CMD_DB_RRQ => Some(encode_templates(config)),
+                _ => None,
+            };
+            match records {
+                Some(records) => {
+                    session.prepared = (records.len() as u32).to_le_bytes().to_vec();
+                    session.prepared.extend_from_slice(&records);
+                    let mut reply = vec![0u8];
+                    reply.extend_from_slice(&(session.prepared.len() as u32).to_le_bytes());
+                    ok(reply)
+                }
+                None => vec![(CMD_ACK_ERROR, Vec::new())],
+            }
+        }
+        CMD_DATA_RDY if data.len() >= 8 => {
+            let start = i32::from_le_bytes([data[0], data[1], data[2], data[3]]).max(0) as usize;
+            let size = i32::from_le_bytes([data[4], data[5], data[6], data[7]]).max(0) as usize;
+            let end = (start + size).min(session.prepared.len());
+            let chunk = session.prepared.get(start..end).unwrap_or_default().to_vec();
+            vec![(CMD_DATA, chunk), (CMD_ACK_OK, Vec::new())]
+        }
+        CMD_GET_USER_TEMPLATE if data.len() >= 3 => {
+            let uid = u16::from_le_bytes([data[0], data[1]]);
+            let fid = data[2];
+            let user = config.users.iter().find(|u| u.uid == uid);
+            let enrolled = user.map(|u| if fid == 50 { u.face } else { u.fingers.contains(&fid) });
+            match (user, enrolled) {
+                (Some(user), Some(true)) if fid == 50 => {
+                    let template = template_bytes(&user.user_id, fid);
+                    if config.face_reply == CMD_PREPARE_DATA {
+                        let size = (template.len() as u32).to_le_bytes().to_vec();
+                        vec![(CMD_PREPARE_DATA, size), (CMD_DATA, template), (CMD_ACK_OK, Vec::new())]
+                    } else {
+                        vec![(config.face_reply, template)]
+                    }
+                }
+                (Some(user), Some(true)) => ok(finger_template(config, user, fid)),
+                _ => vec![(CMD_ACK_ERROR, Vec::new())],
+            }
+        }
+        // Bare CMD_ATTLOG_RRQ and anything else: make the client use the buffered path
+        _ => vec![(CMD_ACK_ERROR, Vec::new())],
+    }
+}
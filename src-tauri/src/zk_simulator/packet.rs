@@ -0,0 +1,142 @@
+//! Protocol constants, packet framing, checksum, commkey and time encoding
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+pub(super) const USHRT_MAX: u16 = 65535;
+pub(super) const MAGIC_1: u16 = 0x5050;
+pub(super) const MAGIC_2: u16 = 0x7D82;
+
+pub(super) const CMD_DB_RRQ: u16 = 7;
+pub(super) const CMD_USER_WRQ: u16 = 8;
+pub(super) const CMD_SAVE_USERTEMPS: u16 = 110;
+pub(super) const CMD_PREPARE_DATA: u16 = 1500;
+pub(super) const CMD_GET_USER_TEMPLATE: u16 = 88;
+pub(super) const CMD_USERTEMP_RRQ: u16 = 9;
+pub(super) const CMD_OPTIONS_RRQ: u16 = 11;
+pub(super) const CMD_OPTIONS_WRQ: u16 = 12;
+pub(super) const CMD_REFRESHOPTION: u16 = 1014;
+pub(super) const CMD_ATTLOG_RRQ: u16 = 13;
+pub(super) const CMD_CLEAR_ATTLOG: u16 = 15;
+pub(super) const CMD_DELETE_USER: u16 = 18;
+pub(super) const CMD_GET_FREE_SIZES: u16 = 50;
+pub(super) const CMD_GET_TIME: u16 = 201;
+pub(super) const CMD_SET_TIME: u16 = 202;
+pub(super) const CMD_CONNECT: u16 = 1000;
+pub(super) const CMD_EXIT: u16 = 1001;
+pub(super) const CMD_ENABLEDEVICE: u16 = 1002;
+pub(super) const CMD_DISABLEDEVICE: u16 = 1003;
+pub(super) const CMD_REFRESHDATA: u16 = 1013;
+pub(super) const CMD_UNLOCK: u16 = 31;
+pub(super) const CMD_REG_EVENT: u16 = 500;
+pub(super) const CMD_RESTART: u16 = 1004;
+pub(super) const CMD_POWEROFF: u16 = 1005;
+pub(super) const CMD_TESTVOICE: u16 = 1017;
+pub(super) const CMD_VERSION: u16 = 1100;
+pub(super) const CMD_AUTH: u16 = 1102;
+pub(super) const CMD_DATA: u16 = 1501;
+pub(super) const CMD_FREE_DATA: u16 = 1502;
+pub(super) const CMD_DATA_WRRQ: u16 = 1503;
+pub(super) const CMD_DATA_RDY: u16 = 1504;
+pub(super) const CMD_ACK_OK: u16 = 2000;
+pub(super) const CMD_ACK_ERROR: u16 = 2001;
+pub(super) const CMD_ACK_UNAUTH: u16 = 2005;
+
+pub(super) const EF_ATTLOG: u32 = 1;
+
+pub(super) fn fixed(value: &str, len: usize) -> Vec<u8> {
+    pad(value.as_bytes(), len)
+}
+
+/// Truncate or NUL-pad to `len`; truncation may cut a multi-byte character, as devices do
+pub(super) fn pad(bytes: &[u8], len: usize) -> Vec<u8> {
+    let mut out = bytes.to_vec();
+    out.resize(len, 0);
+    out
+}
+
+pub(super) fn nul_terminated(value: &str) -> Vec<u8> {
+    let mut out = value.as_bytes().to_vec();
+    out.push(0);
+    out
+}
+
+/// pyzk __create_checksum
+pub(super) fn checksum(data: &[u8]) -> u16 {
+    let mut sum: i64 = 0;
+    for pair in data.chunks(2) {
+        sum += if pair.len() == 2 { u16::from_le_bytes([pair[0], pair[1]]) as i64 } else { pair[0] as i64 };
+        if sum > USHRT_MAX as i64 {
+            sum -= USHRT_MAX as i64;
+        }
+    }
+    let mut sum = !sum;
+    while sum < 0 {
+        sum += USHRT_MAX as i64;
+    }
+    sum as u16
+}
+
+/// Clients checksum the header before bumping the reply id, so undo that first
+pub(super) fn checksum_ok(packet: &[u8]) -> bool {
+    let sent = u16::from_le_bytes([packet[2], packet[3]]);
+    let reply_id = u16::from_le_bytes([packet[6], packet[7]]);
+    let previous = if reply_id == 0 { USHRT_MAX - 1 } else { reply_id - 1 };
+
+    let mut buf = packet.to_vec();
+    buf[2..4].copy_from_slice(&[0, 0]);
+    buf[6..8].copy_from_slice(&previous.to_le_bytes());
+    checksum(&buf) == sent
+}
+
+pub(super) fn reply_packet(cmd: u16, session_id: u16, reply_id: u16, data: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(8 + data.len());
+    packet.extend_from_slice(&cmd.to_le_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(&session_id.to_le_bytes());
+    packet.extend_from_slice(&reply_id.to_le_bytes());
+    packet.extend_from_slice(data);
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_le_bytes());
+    packet
+}
+
+/// Wrap a packet in the TCP top header (magic + length)
+pub(super) fn tcp_frame(packet: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + packet.len());
+    out.extend_from_slice(&MAGIC_1.to_le_bytes());
+    out.extend_from_slice(&MAGIC_2.to_le_bytes());
+    out.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    out.extend_from_slice(packet);
+    out
+}
+
+/// pyzk make_commkey
+pub(super) fn make_commkey(key: u32, session_id: u16) -> [u8; 4] {
+    let mut k: u32 = 0;
+    for i in 0..32 {
+        k = (k << 1) | ((key >> i) & 1);
+    }
+    let k = k.wrapping_add(session_id as u32).to_le_bytes();
+    let k = [k[0] ^ b'Z', k[1] ^ b'K', k[2] ^ b'S', k[3] ^ b'O'];
+    // Swap the two halves, then mix in the tick byte
+    let k = [k[2], k[3], k[0], k[1]];
+    let b = 50u8;
+    [k[0] ^ b, k[1] ^ b, b, k[3] ^ b]
+}
+
+pub(super) fn encode_time(t: &NaiveDateTime) -> u32 {
+    ((t.year() as u32 % 100) * 12 * 31 + (t.month() - 1) * 31 + t.day() - 1) * 86400
+        + (t.hour() * 60 + t.minute()) * 60
+        + t.second()
+}
+
+pub(super) fn decode_time(t: u32) -> NaiveDateTime {
+    let (second, t) = (t % 60, t / 60);
+    let (minute, t) = (t % 60, t / 60);
+    let (hour, t) = (t % 24, t / 24);
+    let (day, t) = (t % 31 + 1, t / 31);
+    let (month, t) = (t % 12 + 1, t / 12);
+    NaiveDate::from_ymd_opt(t as i32 + 2000, month, day)
+        .and_then(|d| d.and_hms_opt(hour, minute, second))
+        .unwrap_or_default()
+}
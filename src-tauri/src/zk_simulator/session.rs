@@ -0,0 +1,94 @@
+//! Per-connection sessions over TCP and UDP
+
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use super::*;
+
+/// Per-connection protocol state
+pub(super) struct Session {
+    pub(super) id: u16,
+    pub(super) connected: bool,
+    pub(super) authenticated: bool,
+    pub(super) event_flags: u32,       // set by CMD_REG_EVENT
+    pub(super) prepared: Vec<u8>,      // buffer announced by the last CMD_DATA_WRRQ
+    pub(super) uploaded: Vec<u8>,      // buffer received with CMD_PREPARE_DATA + CMD_DATA
+}
+
+impl Session {
+    pub(super) fn new(id: u16) -> Self {
+        Session {
+            id,
+            connected: false,
+            authenticated: false,
+            event_flags: 0,
+            prepared: Vec::new(),
+            uploaded: Vec::new(),
+        }
+    }
+}
+
+/// Handle one packet (ZK header + payload); returns the reply packets without the TCP top header
+pub(super) fn respond(session: &mut Session, state: &Mutex<SimConfig>, packet: &[u8]) -> Vec<Vec<u8>> {
+    let reply_id = u16::from_le_bytes([packet[6], packet[7]]);
+    let replies = if checksum_ok(packet) {
+        let mut config = state.lock().unwrap();
+        handle(session, &mut config, packet)
+    } else {
+        vec![(CMD_ACK_ERROR, Vec::new())]
+    };
+    replies.into_iter()
+        .map(|(reply_cmd, data)| reply_packet(reply_cmd, session.id, reply_id, &data))
+        .collect()
+}
+
+pub(super) async fn serve(mut stream: TcpStream, state: Arc<Mutex<SimConfig>>, mut events: broadcast::Receiver<SimPunch>, session_id: u16) {
+    let mut session = Session::new(session_id);
+
+    loop {
+        // Push live punches while waiting for the client's next packet
+        // (peek leaves the packet in the socket, so losing the race drops nothing)
+        let mut probe = [0u8; 1];
+        tokio::select! {
+            peeked = stream.peek(&mut probe) => if !matches!(peeked, Ok(n) if n > 0) {
+                return;
+            },
+            Ok(punch) = events.recv() => {
+                if session.event_flags & EF_ATTLOG != 0 {
+                    let payload = encode_event(&state.lock().unwrap(), &punch);
+                    let packet = reply_packet(CMD_REG_EVENT, session.id, 0, &payload);
+                    if stream.write_all(&tcp_frame(&packet)).await.is_err() {
+                        return;
+                    }
+                }
+                continue;
+            }
+        }
+
+        // Port scans connect and hang up without a word
+        let mut top = [0u8; 8];
+        if stream.read_exact(&mut top).await.is_err() {
+            return;
+        }
+        if u16::from_le_bytes([top[0], top[1]]) != MAGIC_1 || u16::from_le_bytes([top[2], top[3]]) != MAGIC_2 {
+            return;
+        }
+        let length = u32::from_le_bytes([top[4], top[5], top[6], top[7]]) as usize;
+        let mut packet = vec![0u8; length];
+        if length < 8 || stream.read_exact(&mut packet).await.is_err() {
+            return;
+        }
+
+        let cmd = u16::from_le_bytes([packet[0], packet[1]]);
+        for reply in respond(&mut session, &state, &packet) {
+            if stream.write_all(&tcp_frame(&reply)).await.is_err() {
+                return;
+            }
+        }
+        // Restart and power off drop the connection like a real terminal
+        if matches!(cmd, CMD_EXIT | CMD_RESTART | CMD_POWEROFF) {
+            return;
+        }
+    }
+}
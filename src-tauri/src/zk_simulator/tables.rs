@@ -0,0 +1,205 @@
+//! Encoding of the user, template, attendance and event tables, and decoding of uploads
+
+use chrono::{Datelike, Timelike};
+use super::*;
+
+pub(super) fn free_sizes(config: &SimConfig) -> Vec<u8> {
+    let mut fields = [0i32; 20];
+    fields[4] = config.users.len() as i32;
+    fields[6] = config.users.iter().map(|u| u.fingers.len()).sum::<usize>() as i32;
+    fields[8] = config.reported_records.unwrap_or(config.punches.len() as u32) as i32;
+    fields[12] = config.users.iter().filter(|u| u.card != 0).count() as i32;
+    fields[14] = 3000;
+    fields[15] = config.user_capacity as i32;
+    fields[16] = 100_000;
+    fields[17] = 3000 - fields[6];
+    fields[18] = fields[15] - fields[4];
+    fields[19] = 100_000 - fields[8];
+    let mut out: Vec<u8> = fields.iter().flat_map(|f| f.to_le_bytes()).collect();
+    if config.face_capacity > 0 {
+        // faces used, (unused), face capacity
+        let faces = config.users.iter().filter(|u| u.face).count() as i32;
+        for f in [faces, 0, config.face_capacity as i32] {
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+    }
+    out
+}
+
+/// Template table: size(H), uid(H), fid(b), valid(b), template
+pub(super) fn encode_templates(config: &SimConfig) -> Vec<u8> {
+    let mut out = Vec::new();
+    for user in &config.users {
+        for &fid in &user.fingers {
+            let template = finger_template(config, user, fid);
+            out.extend_from_slice(&(6 + template.len() as u16).to_le_bytes());
+            out.extend_from_slice(&user.uid.to_le_bytes());
+            out.push(fid);
+            out.push(1);
+            out.extend(template);
+        }
+    }
+    out
+}
+
+pub(super) fn finger_template(config: &SimConfig, user: &SimUser, fid: u8) -> Vec<u8> {
+    config.templates.get(&(user.uid, fid)).cloned().unwrap_or_else(|| template_bytes(&user.user_id, fid))
+}
+
+/// Stand-in template data, distinct per person and finger (so the same on every device)
+pub(super) fn template_bytes(user_id: &str, fid: u8) -> Vec<u8> {
+    let mut out = b"TPL".to_vec();
+    out.extend_from_slice(user_id.as_bytes());
+    out.push(fid);
+    out
+}
+
+pub(super) fn encode_users(config: &SimConfig) -> Vec<u8> {
+    let mut out = Vec::new();
+    for user in &config.users {
+        out.extend_from_slice(&user.uid.to_le_bytes());
+        out.push(user.privilege);
+        if config.user_size == 28 {
+            // <HB5s8sIxBhI
+            out.extend(fixed(&user.password, 5));
+            out.extend(pad(&config.name_encoding.encode(&user.name).0, 8));
+            out.extend_from_slice(&user.card.to_le_bytes());
+            out.push(0);
+            out.push(user.group_id.parse().unwrap_or(0));
+            out.extend_from_slice(&0i16.to_le_bytes());
+            out.extend_from_slice(&user.user_id.parse::<u32>().unwrap_or(0).to_le_bytes());
+        } else {
+            // <HB8s24sIx7sx24s
+            out.extend(fixed(&user.password, 8));
+            out.extend(pad(&config.name_encoding.encode(&user.name).0, 24));
+            out.extend_from_slice(&user.card.to_le_bytes());
+            out.push(0);
+            out.extend(fixed(&user.group_id, 7));
+            out.push(0);
+            out.extend(fixed(&user.user_id, 24));
+        }
+    }
+    out
+}
+
+/// Apply an HR_save_usertemplates upload: users (each prefixed with 2), then
+/// the finger table (2, uid, 0x10 + fid, offset). Template bytes are not kept.
+pub(super) fn save_user_templates(config: &mut SimConfig, upload: &[u8]) -> Option<()> {
+    let len = |at: usize| upload.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    let (user_len, table_len, finger_len) = (len(0)?, len(4)?, len(8)?);
+    let users = upload.get(12..12 + user_len)?;
+    let table = upload.get(12 + user_len..12 + user_len + table_len)?;
+    let fingers = upload.get(12 + user_len + table_len..12 + user_len + table_len + finger_len)?;
+
+    for record in users.chunks_exact(config.user_size + 1) {
+        let user = decode_user(config, &record[1..]);
+        config.users.retain(|u| u.uid != user.uid);
+        config.templates.retain(|&(uid, _), _| uid != user.uid);
+        config.users.push(user);
+    }
+    // table entry: marker, uid(H), 0x10 + fid, offset(I) of size(H) + template in the finger block
+    for entry in table.chunks_exact(8) {
+        let uid = u16::from_le_bytes([entry[1], entry[2]]);
+        let fid = entry[3].wrapping_sub(0x10);
+        let offset = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]) as usize;
+        let size = fingers.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)?;
+        let template = fingers.get(offset + 2..offset + 2 + size)?;
+        if let Some(user) = config.users.iter_mut().find(|u| u.uid == uid) {
+            if !user.fingers.contains(&fid) {
+                user.fingers.push(fid);
+            }
+            config.templates.insert((uid, fid), template.to_vec());
+        }
+    }
+    config.users.sort_by_key(|u| u.uid);
+    Some(())
+}
+
+pub(super) fn decode_user(config: &SimConfig, record: &[u8]) -> SimUser {
+    let text = |bytes: &[u8]| {
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+    let name = |bytes: &[u8]| {
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        config.name_encoding.decode(&bytes[..end]).0.into_owned()
+    };
+    let uid = u16::from_le_bytes([record[0], record[1]]);
+    let mut user = SimUser::new(uid, "", "");
+    user.privilege = record[2];
+    if config.user_size == 28 {
+        user.password = text(&record[3..8]);
+        user.name = name(&record[8..16]);
+        user.card = u32::from_le_bytes([record[16], record[17], record[18], record[19]]);
+        user.group_id = record[21].to_string();
+        user.user_id = u32::from_le_bytes([record[24], record[25], record[26], record[27]]).to_string();
+    } else {
+        user.password = text(&record[3..11]);
+        user.name = name(&record[11..35]);
+        user.card = u32::from_le_bytes([record[35], record[36], record[37], record[38]]);
+        user.group_id = text(&record[40..47]);
+        user.user_id = text(&record[48..72]);
+    }
+    user
+}
+
+pub(super) fn encode_punches(config: &SimConfig) -> Vec<u8> {
+    let mut out = Vec::new();
+    for p in &config.punches {
+        let time = encode_time(&p.time).to_le_bytes();
+        match config.record_size {
+            8 => {
+                // <HB4sB
+                out.extend_from_slice(&p.uid.to_le_bytes());
+                out.push(p.status);
+                out.extend_from_slice(&time);
+                out.push(p.punch);
+            }
+            16 => {
+                // <I4sBB2sI
+                out.extend_from_slice(&p.user_id.parse::<u32>().unwrap_or(p.uid as u32).to_le_bytes());
+                out.extend_from_slice(&time);
+                out.push(p.status);
+                out.push(p.punch);
+                out.extend_from_slice(&[0, 0]);
+                out.extend_from_slice(&p.workcode.to_le_bytes());
+            }
+            _ => {
+                // <H24sB4sB8s
+                out.extend_from_slice(&p.uid.to_le_bytes());
+                out.extend(fixed(&p.user_id, 24));
+                out.push(p.status);
+                out.extend_from_slice(&time);
+                out.push(p.punch);
+                out.extend_from_slice(&p.workcode.to_le_bytes());
+                out.extend_from_slice(&[0u8; 4]);
+            }
+        }
+    }
+    out
+}
+
+/// EF_ATTLOG event payload; the layout follows the terminal's record size
+/// (10, 12 and 36 bytes, as decoded by pyzk live_capture)
+pub(super) fn encode_event(config: &SimConfig, p: &SimPunch) -> Vec<u8> {
+    let mut out = Vec::new();
+    match config.record_size {
+        8 => out.extend_from_slice(&p.user_id.parse::<u16>().unwrap_or(p.uid).to_le_bytes()),
+        16 => out.extend_from_slice(&p.user_id.parse::<u32>().unwrap_or(p.uid as u32).to_le_bytes()),
+        _ => out.extend(fixed(&p.user_id, 24)),
+    }
+    out.push(p.status);
+    out.push(p.punch);
+    out.extend_from_slice(&[
+        (p.time.year() - 2000) as u8,
+        p.time.month() as u8,
+        p.time.day() as u8,
+        p.time.hour() as u8,
+        p.time.minute() as u8,
+        p.time.second() as u8,
+    ]);
+    if config.record_size == 40 {
+        out.extend_from_slice(&p.workcode.to_le_bytes());
+    }
+    out
+}
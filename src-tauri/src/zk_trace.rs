@@ -0,0 +1,289 @@
+//! Opt-in capture of ZKTeco sessions to JSON-lines trace files, and replay of
+//! those traces so a misparsing firmware can be reproduced without the device
+//!
+//! The commkey and the PIN and card fields of user writes are blanked before
+//! they reach the file. Everything the device sends back is kept as received,
+//! so a trace of a user or template download still holds user passwords, card
+//! numbers and fingerprint templates; treat trace files as credentials.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use log::{info, warn};
+use crate::zk_error::ZkError;
+use crate::zkteco_client::{CMD_AUTH, CMD_USER_WRQ};
+
+pub const TRACE_FORMAT: &str = "zktrace";
+pub const TRACE_VERSION: u32 = 1;
+
+/// Directory new sessions are traced into; None disables tracing
+static TRACE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub fn set_trace_dir(dir: Option<PathBuf>) -> Result<(), String> {
+    if let Some(dir) = &dir {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        info!("🧾 Protocol tracing to {}", dir.display());
+    } else {
+        info!("🧾 Protocol tracing off");
+    }
+    *TRACE_DIR.lock().map_err(|e| format!("Trace lock poisoned: {}", e))? = dir;
+    Ok(())
+}
+
+pub fn trace_dir() -> Option<PathBuf> {
+    TRACE_DIR.lock().ok()?.clone()
+}
+
+/// First line of a trace file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceHeader {
+    pub format: String,
+    pub version: u32,
+    pub transport: String,      // "tcp" or "udp"
+    pub peer: String,
+    pub started_at: String,
+}
+
+/// One framed packet. `kind` is "packet" (one ZK packet, TCP top header stripped),
+/// "burst" (a single raw TCP read, starting at the ZK header), "raw" (bytes
+/// continuing a TCP frame) or "error" (a read that failed, e.g. timed out).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub t_ms: u64,
+    pub dir: String,            // "tx" or "rx"
+    pub kind: String,
+    pub cmd: Option<u16>,
+    pub hex: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<TraceFailure>,
+}
+
+/// Why a read failed, so replay fails the same way at the same point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceFailure {
+    pub code: String,           // ZkError::code()
+    pub message: String,
+}
+
+impl TraceEntry {
+    fn bytes(&self) -> Result<Vec<u8>, ZkError> {
+        crate::zkteco_client::from_hex(&self.hex)
+            .map_err(|e| ZkError::Protocol(format!("Corrupt trace entry: {}", e)))
+    }
+}
+
+/// Entries are buffered and written out when the buffer fills or the session
+/// ends, so tracing adds no file I/O per packet to the session it observes
+pub struct TraceWriter {
+    out: BufWriter<File>,
+    started: Instant,
+    path: PathBuf,
+}
+
+const TRACE_BUFFER: usize = 256 * 1024;
+
+/// Blank the password and card of a user record written to the device; the
+/// record length tells the 28-byte layout from the 72-byte one
+fn redact_user_record(record: &mut [u8]) {
+    let (password, card) = match record.len() {
+        28 => (3..8, 16..20),
+        72 => (3..11, 35..39),
+        _ => return,
+    };
+    record[password].fill(0);
+    record[card].fill(0);
+}
+
+impl TraceWriter {
+    /// Start a trace for a new session if tracing is enabled
+    pub fn start(transport: &str, peer: &str) -> Option<Self> {
+        let dir = trace_dir()?;
+        let path = dir.join(format!(
+            "{}_{}_{}.zktrace.jsonl",
+            peer.replace([':', '.'], "-"),
+            transport,
+            chrono::Local::now().format("%Y%m%d_%H%M%S%.3f"),
+        ));
+        Self::create(path, transport, peer)
+    }
+
+    /// Trace a session to the given file
+    pub fn create(path: PathBuf, transport: &str, peer: &str) -> Option<Self> {
+        let started_at = chrono::Local::now();
+        let mut writer = match File::create(&path) {
+            Ok(file) => TraceWriter { out: BufWriter::with_capacity(TRACE_BUFFER, file), started: Instant::now(), path },
+            Err(e) => {
+                warn!("Cannot create trace file {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let header = TraceHeader {
+            format: TRACE_FORMAT.to_string(),
+            version: TRACE_VERSION,
+            transport: transport.to_string(),
+            peer: peer.to_string(),
+            started_at: started_at.to_rfc3339(),
+        };
+        writer.write_line(&header);
+        info!("🧾 Tracing {} session with {} to {}", transport, peer, writer.path.display());
+        Some(writer)
+    }
+
+    pub fn record(&mut self, dir: &str, kind: &str, data: &[u8]) {
+        let cmd = (kind != "raw" && data.len() >= 2).then(|| u16::from_le_bytes([data[0], data[1]]));
+        // The commkey is derived from the device password; keep it out of files that get shared
+        let hex = match cmd {
+            Some(CMD_AUTH) if dir == "tx" && data.len() > 8 => {
+                let mut redacted = data.to_vec();
+                redacted[8..].fill(0);
+                crate::zkteco_client::to_hex(&redacted)
+            }
+            Some(CMD_USER_WRQ) if dir == "tx" => {
+                let mut redacted = data.to_vec();
+                redact_user_record(&mut redacted[8.min(data.len())..]);
+                crate::zkteco_client::to_hex(&redacted)
+            }
+            _ => crate::zkteco_client::to_hex(data),
+        };
+        let entry = TraceEntry {
+            t_ms: self.started.elapsed().as_millis() as u64,
+            dir: dir.to_string(),
+            kind: kind.to_string(),
+            cmd,
+            hex,
+            error: None,
+        };
+        self.write_line(&entry);
+    }
+
+    /// Record a read that failed instead of returning bytes
+    pub fn record_failure(&mut self, error: &ZkError) {
+        let entry = TraceEntry {
+            t_ms: self.started.elapsed().as_millis() as u64,
+            dir: "rx".to_string(),
+            kind: "error".to_string(),
+            cmd: None,
+            hex: String::new(),
+            error: Some(TraceFailure { code: error.code().to_string(), message: error.to_string() }),
+        };
+        self.write_line(&entry);
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) {
+        // Tracing must never break the session it observes
+        let result = serde_json::to_string(value)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(self.out, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Trace write to {} failed: {}", self.path.display(), e);
+        }
+    }
+}
+
+impl Drop for TraceWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.out.flush() {
+            warn!("Trace write to {} failed: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// A saved trace played back in place of a socket
+pub struct TraceReplay {
+    pub header: TraceHeader,
+    entries: VecDeque<TraceEntry>,
+}
+
+impl TraceReplay {
+    pub fn load(path: &Path) -> Result<Self, ZkError> {
+        let file = File::open(path)
+            .map_err(|e| ZkError::Other(format!("Failed to open {}: {}", path.display(), e)))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let first = lines.next()
+            .ok_or_else(|| ZkError::Other(format!("{} is empty", path.display())))?
+            .map_err(|e| ZkError::Other(format!("Failed to read {}: {}", path.display(), e)))?;
+        let header: TraceHeader = serde_json::from_str(&first)
+            .map_err(|e| ZkError::Other(format!("Not a trace file ({}): {}", path.display(), e)))?;
+        if header.format != TRACE_FORMAT || header.version > TRACE_VERSION {
+            return Err(ZkError::Other(format!(
+                "Unsupported trace {} v{} (expected {} v{})",
+                header.format, header.version, TRACE_FORMAT, TRACE_VERSION
+            )));
+        }
+
+        let mut entries = VecDeque::new();
+        for (n, line) in lines.enumerate() {
+            let line = line.map_err(|e| ZkError::Other(format!("Failed to read {}: {}", path.display(), e)))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: TraceEntry = serde_json::from_str(&line)
+                .map_err(|e| ZkError::Other(format!("Bad trace entry on line {}: {}", n + 2, e)))?;
+            entries.push_back(entry);
+        }
+
+        info!("🧾 Replaying {} ({} entries, {} from {})",
+            path.display(), entries.len(), header.transport, header.peer);
+        Ok(TraceReplay { header, entries })
+    }
+
+    pub fn is_tcp(&self) -> bool {
+        self.header.transport == "tcp"
+    }
+
+    /// Check an outgoing packet against the next recorded one. Only the command
+    /// is compared; session ids, checksums and timestamps legitimately differ.
+    pub fn expect_send(&mut self, packet: &[u8]) -> Result<(), ZkError> {
+        let sent = u16::from_le_bytes([packet[0], packet[1]]);
+        let entry = self.next("tx")?;
+        match entry.cmd {
+            Some(cmd) if cmd != sent => Err(ZkError::Protocol(format!(
+                "Replay diverged at {}ms: sent cmd={}, trace has cmd={}", entry.t_ms, sent, cmd
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Next recorded inbound bytes of the given kind, or the recorded failure of that read
+    pub fn next_rx(&mut self, kind: &str) -> Result<Vec<u8>, ZkError> {
+        let entry = self.peek("rx")?;
+        if let Some(failure) = &entry.error {
+            let error = ZkError::from_code(&failure.code, failure.message.clone());
+            self.entries.pop_front();
+            return Err(error);
+        }
+        if entry.kind != kind {
+            return Err(ZkError::Protocol(format!(
+                "Replay diverged at {}ms: reading {} but trace has {}", entry.t_ms, kind, entry.kind
+            )));
+        }
+        let bytes = entry.bytes();
+        self.entries.pop_front();
+        bytes
+    }
+
+    fn next(&mut self, dir: &str) -> Result<TraceEntry, ZkError> {
+        self.peek(dir)?;
+        Ok(self.entries.pop_front().expect("peeked entry"))
+    }
+
+    /// The next entry if it goes in direction `dir`; a mismatch leaves it in place
+    fn peek(&self, dir: &str) -> Result<&TraceEntry, ZkError> {
+        let entry = self.entries.front()
+            // The recorded session ended here; to the client it looks like a silent device
+            .ok_or_else(|| ZkError::Timeout("Trace exhausted".to_string()))?;
+        if entry.dir != dir {
+            return Err(ZkError::Protocol(format!(
+                "Replay diverged at {}ms: expected {} but trace has {} cmd={:?}",
+                entry.t_ms, dir, entry.dir, entry.cmd
+            )));
+        }
+        Ok(entry)
+    }
+}
@@ -0,0 +1,223 @@
+//! Full device archives: backup and restore
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use chrono::Local;
+use log::info;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Everything needed to rebuild a terminal: identity, options, users, templates
+/// and the attendance log, saved as one versioned JSON file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceArchive {
+    pub format_version: u32,
+    pub created_at: String,
+    pub device_info: DeviceInfo,
+    pub device_key: String,
+    pub fp_version: String,
+    pub options: Vec<DeviceOption>,
+    pub users: Vec<UserInput>,
+    pub templates: Vec<FingerTemplate>,
+    pub attendance: Vec<AttendanceRecord>,  // Kept for the record; logs cannot be written back
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceArchiveSummary {
+    pub path: String,
+    pub device_key: String,
+    pub options: usize,
+    pub users: usize,
+    pub templates: usize,
+    pub attendance_records: usize,
+}
+
+/// What restoring an archive changes on a device, worked out before anything is written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveRestorePlan {
+    pub path: String,
+    pub source_device: DeviceInfo,
+    pub target_device: DeviceInfo,
+    pub target_key: String,
+    pub dry_run: bool,                      // true = nothing was written
+    pub users_added: Vec<String>,           // Badge IDs
+    pub users_updated: Vec<String>,         // Name, privilege, password, card or group differ
+    pub users_unchanged: usize,
+    pub users_only_on_device: Vec<String>,  // Left in place
+    pub templates: usize,
+    pub option_changes: Vec<OptionChange>,  // previous = device value, value = archive value
+    pub warnings: Vec<String>,              // Users, templates and options that are skipped
+}
+
+const DEVICE_ARCHIVE_VERSION: u32 = 1;
+
+// Options never restored from an archive: they would cut the connection or lock us out
+const ARCHIVE_SKIPPED_OPTIONS: &[&str] = &["MAC", "IPAddress", "NetMask", "GATEIPAddress", "COMKey"];
+
+/// Save a device's info, known options, users, templates and attendance log to one archive file
+pub async fn backup_device(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<DeviceArchiveSummary, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    
+    let result: Result<DeviceArchive, ZkError> = async {
+        client.load_settings(&ip, port, &settings).await;
+        let device_info = client.get_device_info().await;
+        let device_key = device_key(&device_info, &ip, port);
+        let fp_version = client.get_option("~ZKFPVersion").await.unwrap_or_default();
+        
+        let mut options = Vec::with_capacity(KNOWN_OPTIONS.len());
+        for key in KNOWN_OPTIONS {
+            let value = client.read_option(key).await?;
+            options.push(DeviceOption { key: key.to_string(), value });
+        }
+        
+        client.disable_device().await?;
+        let users = client.get_users().await?;
+        let templates = client.get_templates().await?;
+        // An unreadable count would silently leave the log out of the archive
+        let record_count = client.read_capacity().await?.records.used;
+        let attendance = if record_count == 0 {
+            Vec::new()
+        } else {
+            client.get_attendance(&users, record_count).await?
+        };
+        
+        Ok(DeviceArchive {
+            format_version: DEVICE_ARCHIVE_VERSION,
+            created_at: Local::now().to_rfc3339(),
+            device_info,
+            device_key,
+            fp_version,
+            options,
+            users: users.iter().map(User::to_input).collect(),
+            templates,
+            attendance,
+        })
+    }.await;
+    
+    client.disconnect().await?;
+    let archive = result?;
+    
+    let json = serde_json::to_string_pretty(&archive)
+        .map_err(|e| ZkError::Other(format!("Failed to serialize archive: {}", e)))?;
+    std::fs::write(&path, json)
+        .map_err(|e| ZkError::Other(format!("Failed to write {}: {}", path, e)))?;
+    
+    info!("💾 Archived {} ({} users, {} templates, {} records) to {}",
+        archive.device_key, archive.users.len(), archive.templates.len(), archive.attendance.len(), path);
+    Ok(DeviceArchiveSummary {
+        path,
+        device_key: archive.device_key,
+        options: archive.options.iter().filter(|o| o.value.is_some()).count(),
+        users: archive.users.len(),
+        templates: archive.templates.len(),
+        attendance_records: archive.attendance.len(),
+    })
+}
+
+/// Restore an archive onto a device, which may be a different model. With
+/// `dry_run` only the plan is returned; otherwise new and changed users, their
+/// templates and options are written. Unsupported options are skipped with a warning.
+pub async fn restore_device(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    dry_run: bool,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<ArchiveRestorePlan, ZkError> {
+    let ip = ip.to_string();
+    
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| ZkError::Other(format!("Failed to read {}: {}", path, e)))?;
+    let archive: DeviceArchive = serde_json::from_str(&content)
+        .map_err(|e| ZkError::Other(format!("Invalid device archive: {}", e)))?;
+    if archive.format_version > DEVICE_ARCHIVE_VERSION {
+        return Err(ZkError::Other(format!("Archive format v{} is newer than supported v{}",
+            archive.format_version, DEVICE_ARCHIVE_VERSION)));
+    }
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    
+    let result: Result<ArchiveRestorePlan, ZkError> = async {
+        client.load_settings(&ip, port, &settings).await;
+        let target_device = client.get_device_info().await;
+        let target_key = device_key(&target_device, &ip, port);
+        let mut plan = ArchiveRestorePlan {
+            path: path.clone(),
+            source_device: archive.device_info.clone(),
+            target_device,
+            target_key,
+            dry_run,
+            users_added: Vec::new(),
+            users_updated: Vec::new(),
+            users_unchanged: 0,
+            users_only_on_device: Vec::new(),
+            templates: 0,
+            option_changes: Vec::new(),
+            warnings: Vec::new(),
+        };
+        
+        client.disable_device().await?;
+        let upload = client.plan_user_upload(&archive.users, &archive.templates, &archive.fp_version).await?;
+        plan.users_added = upload.added.clone();
+        plan.users_updated = upload.updated.clone();
+        plan.users_unchanged = upload.unchanged;
+        plan.users_only_on_device = upload.only_on_device.iter().map(|(_, id)| id.clone()).collect();
+        plan.templates = upload.templates.len();
+        plan.warnings = upload.warnings.clone();
+        
+        for option in &archive.options {
+            let value = match &option.value {
+                Some(value) if !option.key.starts_with('~') => value,
+                _ => continue,
+            };
+            if ARCHIVE_SKIPPED_OPTIONS.contains(&option.key.as_str()) {
+                continue;
+            }
+            match client.read_option(&option.key).await? {
+                None => plan.warnings.push(format!("Option {} is not supported by this device", option.key)),
+                Some(current) if current == *value => {}
+                Some(current) => plan.option_changes.push(OptionChange {
+                    key: option.key.clone(),
+                    previous: Some(current),
+                    value: Some(value.clone()),
+                    applied: false,
+                    error: None,
+                }),
+            }
+        }
+        
+        if dry_run {
+            return Ok(plan);
+        }
+        
+        if !upload.users.is_empty() {
+            client.save_user_templates(&upload.users, &upload.templates).await?;
+        }
+        if !plan.option_changes.is_empty() {
+            for change in &plan.option_changes {
+                client.write_option(&change.key, change.value.as_deref().unwrap_or("")).await?;
+            }
+            client.refresh_options().await?;
+            plan.option_changes.iter_mut().for_each(|change| change.applied = true);
+        }
+        info!("📥 Restored {} to {}: {} users, {} templates, {} options",
+            archive.device_key, plan.target_key, upload.users.len(), plan.templates, plan.option_changes.len());
+        Ok(plan)
+    }.await;
+    
+    client.disconnect().await?;
+    result
+}
+
+#[cfg(test)]
+mod tests;
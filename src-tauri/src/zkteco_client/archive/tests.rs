@@ -0,0 +1,59 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::collections::HashMap;
+use crate::zk_simulator::{SimConfig, SimUser, Simulator};
+
+#[tokio::test]
+async fn backup_fails_when_record_count_is_unreadable() {
+    let sim = Simulator::start(SimConfig { sizes_unavailable: true, ..device(72, 40) }).await;
+    let path = std::env::temp_dir().join(format!("zk-archive-sizes-{}.json", std::process::id()));
+    
+    assert!(backup_device("127.0.0.1", sim.port, None, path.to_string_lossy().to_string(), settings("archive")).await.is_err());
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn archive_restores_onto_another_model() {
+    let mut source = device(72, 40);
+    source.users[0].fingers = vec![0, 6];
+    source.users[1].user_id = "B-2".to_string();
+    source.options.insert("LockOn".to_string(), "5".to_string());
+    let source = Simulator::start(source).await;
+    
+    let mut target_users = vec![SimUser::new(1, "1003", "Chitra"), SimUser::new(2, "9000", "Old")];
+    target_users[0].privilege = 14;
+    let mut options = HashMap::new();
+    options.insert("LockOn".to_string(), "10".to_string());
+    let target = Simulator::start(SimConfig {
+        users: target_users,
+        options,
+        serial_number: "SIM0000000002".to_string(),
+        ..device(28, 16)
+    }).await;
+    
+    let path = std::env::temp_dir().join(format!("zk-archive-{}.json", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let summary = backup_device("127.0.0.1", source.port, None, path.clone(), settings("archive")).await.unwrap();
+    assert_eq!((summary.users, summary.templates, summary.attendance_records), (3, 2, 4));
+    
+    let plan = restore_device("127.0.0.1", target.port, None, path.clone(), true, settings("archive")).await.unwrap();
+    assert_eq!(plan.users_added, vec!["1001"]);
+    assert_eq!(plan.users_updated, vec!["1003"]);
+    assert_eq!(plan.users_only_on_device, vec!["9000"]);
+    assert_eq!(plan.templates, 2);
+    assert_eq!(plan.option_changes.len(), 1);
+    assert!(plan.warnings.iter().any(|w| w.contains("B-2")));
+    let unchanged = list_device_users("127.0.0.1", target.port, None, settings("archive")).await.unwrap();
+    assert_eq!(unchanged.users.len(), 2);
+    
+    restore_device("127.0.0.1", target.port, None, path.clone(), false, settings("archive")).await.unwrap();
+    let users = list_device_users("127.0.0.1", target.port, None, settings("archive")).await.unwrap().users;
+    let find = |id: &str| users.iter().find(|u| u.user_id == id).unwrap().clone();
+    assert_eq!(users.len(), 3);
+    assert_eq!(find("1001").finger_count, 2);
+    assert_eq!(find("1003").uid, 1);
+    assert_eq!(find("1003").privilege, 0);
+    let report = read_device_options("127.0.0.1", target.port, None, vec!["LockOn".to_string()], settings("archive")).await.unwrap();
+    assert_eq!(report.options[0].value.as_deref(), Some("5"));
+    let _ = std::fs::remove_file(&path);
+}
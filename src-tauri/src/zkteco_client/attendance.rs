@@ -0,0 +1,133 @@
+//! Full attendance downloads
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use log::{info, warn};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttendanceResponse {
+    pub device_info: DeviceInfo,
+    pub records: Vec<AttendanceRecord>,
+    #[serde(default)]
+    pub record_layout: Option<RecordLayout>,
+}
+
+impl ZKClient {
+    pub(super) async fn get_attendance(&mut self, users: &[User], expected_records: u32) -> Result<Vec<AttendanceRecord>, ZkError> {
+        let (records, _) = self.download_attendance(users, Some(expected_records)).await?;
+        Ok(records)
+    }
+    
+    /// Download and parse the whole attendance log, returning the layout used.
+    /// `expected_records` is None when the device did not report its count.
+    pub(super) async fn download_attendance(&mut self, users: &[User], expected_records: Option<u32>) -> Result<(Vec<AttendanceRecord>, Option<RecordLayout>), ZkError> {
+        info!("Fetching attendance logs (expecting {:?})...", expected_records);
+        let may_have_records = expected_records != Some(0);
+        
+        // Try simple read first
+        let (mut data, _) = self.read_simple(CMD_ATTLOG_RRQ).await?;
+        
+        // If empty, try buffered read
+        if data.len() < 4 && may_have_records {
+            let (data2, _) = self.read_with_buffer_pyzk(CMD_ATTLOG_RRQ, 0).await?;
+            data = data2;
+        }
+        
+        // If still empty, try with fct=1
+        if data.len() < 4 && may_have_records {
+            let (data2, _) = self.read_with_buffer_pyzk(CMD_ATTLOG_RRQ, 1).await?;
+            data = data2;
+        }
+        
+        if data.len() < 4 {
+            return Ok((Vec::new(), None));
+        }
+        
+        let total_size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        
+        let layout = match self.settings.record_size {
+            Some(record_size) => RecordLayout {
+                record_size,
+                confidence: 1.0,
+                overridden: true,
+                candidates: Vec::new(),
+            },
+            None => Self::detect_record_layout(&data[4..], total_size, expected_records.unwrap_or(0), users),
+        };
+        if layout.overridden {
+            info!("Attendance layout: {} bytes (device setting)", layout.record_size);
+        } else if layout.confidence < LAYOUT_LOW_CONFIDENCE {
+            warn!("⚠️ Attendance layout guess {} bytes has low confidence ({:.0}%) - set a record layout override for this device",
+                layout.record_size, layout.confidence * 100.0);
+        } else {
+            info!("Attendance layout: {} bytes ({:.0}% confidence)", layout.record_size, layout.confidence * 100.0);
+        }
+        
+        let records = Self::parse_attendance(&data[4..], layout.record_size, users, self.settings.timezone());
+        Ok((records, Some(layout)))
+    }
+}
+
+pub async fn connect_and_fetch_attendance(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<AttendanceResponse, ZkError> {
+    let mut client = ZKClient::connect(ip, port, password.unwrap_or(0)).await?;
+    fetch_attendance_session(&mut client, ip, port, &settings).await
+}
+
+/// Re-run an attendance download against a trace saved with protocol tracing on.
+/// The client must issue the same commands as when the trace was recorded, so the
+/// traced device's settings are looked up just as they were during the download.
+pub async fn replay_attendance_trace(path: &str, settings: Arc<DeviceSettingsStore>) -> Result<AttendanceResponse, ZkError> {
+    let mut client = ZKClient::from_trace(Path::new(path)).await?;
+    fetch_attendance_session(&mut client, "replay", 0, &settings).await
+}
+
+async fn fetch_attendance_session(
+    client: &mut ZKClient,
+    ip: &str,
+    port: u16,
+    settings: &DeviceSettingsStore,
+) -> Result<AttendanceResponse, ZkError> {
+    client.load_settings(ip, port, settings).await;
+    let device_info = client.get_device_info().await;
+    
+    if let Err(e) = client.disable_device().await {
+        warn!("Failed to disable device: {}", e);
+    }
+    
+    // The count only helps pick the record layout, so a device that will not
+    // report it is still read; a broken connection is not
+    let record_count = match client.read_sizes().await {
+        Ok((_, _, record_count)) => Some(record_count),
+        Err(e @ (ZkError::UnexpectedCommand { .. } | ZkError::ShortPacket(_))) => {
+            warn!("Could not read the record count, detecting the layout without it: {}", e);
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    
+    let users = client.get_users().await.unwrap_or_else(|_| Vec::new());
+    info!("Users: {}, Expected records: {:?}", users.len(), record_count);
+    
+    let (records, record_layout) = client.download_attendance(&users, record_count).await?;
+    info!("Fetched {} attendance records", records.len());
+    
+    client.disconnect().await?;
+    
+    Ok(AttendanceResponse {
+        device_info,
+        records,
+        record_layout,
+    })
+}
+
+#[cfg(test)]
+mod tests;
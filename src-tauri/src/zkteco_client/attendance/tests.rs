@@ -0,0 +1,82 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::zk_simulator::{SimConfig, SimPunch, Simulator};
+
+#[tokio::test]
+async fn fetches_40_byte_records_with_72_byte_users() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let response = fetch(&sim, None).await.unwrap();
+    
+    assert_eq!(response.device_info.device_name, "SIM-F18");
+    assert_eq!(response.device_info.serial_number, "SIM0000000001");
+    assert_eq!(response.device_info.firmware_version, "Ver 6.60 Sim");
+    assert_eq!(response.records.len(), 4);
+    
+    let first = &response.records[0];
+    assert_eq!(first.user_id, 1001);
+    assert_eq!(first.user_name, "Alice");
+    assert_eq!(first.date, "2024-01-15");
+    assert_eq!(first.time, "09:00:00");
+    assert_eq!(response.records[2].punch, 1);
+    assert_eq!(response.records[2].punch_state, PunchState::CheckOut);
+    assert_eq!(response.records[2].workcode, 7);
+    assert_eq!(response.records[3].user_name, "Chitra");
+    assert_eq!(first.verify, VerifyMethod::Fingerprint);
+    assert_eq!(first.punch_state, PunchState::CheckIn);
+    
+    let layout = response.record_layout.unwrap();
+    assert_eq!(layout.record_size, 40);
+    assert!(!layout.overridden);
+    assert!(layout.confidence >= LAYOUT_LOW_CONFIDENCE);
+}
+
+#[tokio::test]
+async fn fetches_16_byte_records_with_28_byte_users() {
+    let sim = Simulator::start(device(28, 16)).await;
+    let response = fetch(&sim, None).await.unwrap();
+    
+    let summary: Vec<_> = response.records.iter()
+        .map(|r| (r.user_id, r.user_name.as_str(), r.time.as_str()))
+        .collect();
+    assert_eq!(summary, vec![
+        (1001, "Alice", "09:00:00"),
+        (1002, "Bala", "09:05:00"),
+        (1001, "Alice", "18:02:00"),
+        (1003, "Chitra", "08:55:00"),
+    ]);
+    assert_eq!(response.records[2].punch_state, PunchState::CheckOut);
+    assert_eq!(response.records[2].workcode, 7);
+}
+
+#[tokio::test]
+async fn fetches_8_byte_records_keyed_by_uid() {
+    let sim = Simulator::start(device(28, 8)).await;
+    let response = fetch(&sim, None).await.unwrap();
+    
+    assert_eq!(response.records.len(), 4);
+    assert_eq!(response.records[1].user_id, 2);
+    assert_eq!(response.records[1].user_name, "Bala");
+    assert_eq!(response.records[3].date, "2024-01-16");
+}
+
+#[tokio::test]
+async fn empty_log_returns_no_records() {
+    let sim = Simulator::start(SimConfig { punches: Vec::new(), ..device(72, 40) }).await;
+    let response = fetch(&sim, None).await.unwrap();
+    assert!(response.records.is_empty());
+}
+
+#[tokio::test]
+async fn large_log_is_read_in_chunks() {
+    let mut config = device(72, 40);
+    let alice = config.users[0].clone();
+    config.punches = (0..2000u32)
+        .map(|i| SimPunch::new(&alice, at(1 + i / 100, 8 + (i % 100) / 10, i % 10), (i % 2) as u8))
+        .collect();
+    let sim = Simulator::start(config).await;
+    
+    let response = fetch(&sim, None).await.unwrap();
+    assert_eq!(response.records.len(), 2000);
+    assert_eq!(response.records[1999].date, "2024-01-20");
+    assert_eq!(response.records[1999].time, "17:09:00");
+}
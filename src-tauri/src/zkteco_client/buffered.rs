@@ -0,0 +1,165 @@
+//! Bulk table reads (CMD_DATA_WRRQ) and their chunked fallbacks
+
+use log::{debug, info};
+use crate::zk_error::ZkError;
+use super::*;
+
+impl ZKClient {
+    /// Read data using buffered transfer (CMD_DATA_WRRQ)
+    pub(super) async fn read_with_buffer_pyzk(&mut self, command: u16, fct: i32) -> Result<(Vec<u8>, usize), ZkError> {
+        self.read_with_buffer_from(command, fct, 0).await
+    }
+    
+    /// Buffered read that skips the first `skip` bytes of the device buffer.
+    /// Chunked transfers start at the offset so the skipped part is never sent.
+    pub(super) async fn read_with_buffer_from(&mut self, command: u16, fct: i32, skip: usize) -> Result<(Vec<u8>, usize), ZkError> {
+        // pyzk: 0xFFc0 over TCP, 16KB over UDP
+        let max_chunk: usize = if self.transport.is_tcp() { 0xFFc0 } else { 16 * 1024 };
+        
+        let mut cmd_string = Vec::new();
+        cmd_string.push(1u8);
+        cmd_string.extend_from_slice(&(command as i16).to_le_bytes());
+        cmd_string.extend_from_slice(&fct.to_le_bytes());
+        cmd_string.extend_from_slice(&0i32.to_le_bytes());
+        
+        let (mut cmd, all_data) = self.send_command_large_recv(CMD_DATA_WRRQ, &cmd_string).await?;
+        let mut data = if all_data.len() > 8 { all_data[8..].to_vec() } else { Vec::new() };
+        
+        if cmd == CMD_DATA {
+            return Ok(Self::skip_prefix(data, skip));
+        }
+        
+        // Handle empty ACKs - drain follow-up packets
+        if cmd == CMD_ACK_OK && data.len() < 5 {
+            let old_timeout = self.transport.read_timeout();
+            let _ = self.transport.set_read_timeout(Some(std::time::Duration::from_secs(30)));
+
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(35);
+            let mut seen = 0usize;
+
+            while std::time::Instant::now() < deadline && seen < 25 {
+                match self.recv_packet().await {
+                    Ok((cmd2, data2)) => {
+                        seen += 1;
+                        cmd = cmd2;
+                        data = data2;
+
+                        if cmd == CMD_DATA { return Ok(Self::skip_prefix(data, skip)); }
+                        if cmd == CMD_PREPARE_DATA { break; }
+                        if cmd == CMD_ACK_OK && data.len() >= 5 { break; }
+                    }
+                    Err(e) => {
+                        if e.is_timeout() {
+                            continue;
+                        }
+                        break;
+                    }
+                }
+            }
+            let _ = self.transport.set_read_timeout(old_timeout);
+        }
+        
+        if cmd == CMD_PREPARE_DATA {
+            if data.len() >= 4 {
+                let size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+                if size > 0 {
+                    let (all_data, _) = self.read_prepare_data_stream(size).await?;
+                    return Ok(Self::skip_prefix(all_data, skip));
+                }
+            }
+            return Ok((Vec::new(), 0));
+        }
+        
+        // Extract size and read chunks
+        if data.len() >= 5 {
+            let size = u32::from_le_bytes([data[1], data[2], data[3], data[4]]) as usize;
+            if size > 0 && size < 100_000_000 {
+                return self.read_chunks(skip.min(size), size, max_chunk).await;
+            }
+        }
+        
+        // Check for second packet in buffer (TCP only, UDP datagrams hold one packet)
+        if self.transport.is_tcp() && data.len() >= 24 {
+            let tcp_magic1 = u16::from_le_bytes([data[0], data[1]]);
+            let tcp_magic2 = u16::from_le_bytes([data[2], data[3]]);
+            
+            if tcp_magic1 == MACHINE_PREPARE_DATA_1 && tcp_magic2 == MACHINE_PREPARE_DATA_2 {
+                let payload2 = &data[16..];
+                if payload2.len() >= 5 {
+                    let size = u32::from_le_bytes([payload2[1], payload2[2], payload2[3], payload2[4]]) as usize;
+                    if size > 0 && size < 100_000_000 {
+                        self.reply_id = u16::from_le_bytes([data[14], data[15]]);
+                        return self.read_chunks(skip.min(size), size, max_chunk).await;
+                    }
+                }
+            }
+        }
+        
+        let _ = self.send_command(CMD_FREE_DATA, &[]).await;
+        Ok((Vec::new(), 0))
+    }
+    
+    fn skip_prefix(data: Vec<u8>, skip: usize) -> (Vec<u8>, usize) {
+        let data = if skip == 0 { data } else { data.get(skip..).map(|d| d.to_vec()).unwrap_or_default() };
+        let len = data.len();
+        (data, len)
+    }
+    
+    /// Read bytes `from..size` of the prepared buffer in chunks
+    async fn read_chunks(&mut self, from: usize, size: usize, max_chunk: usize) -> Result<(Vec<u8>, usize), ZkError> {
+        let remain = (size - from) % max_chunk;
+        let packets = (size - from - remain) / max_chunk;
+        
+        let mut all_data = Vec::with_capacity(size - from);
+        let mut start = from;
+        let start_time = std::time::Instant::now();
+        
+        for i in 0..packets {
+            let chunk = self.read_chunk_pyzk(start, max_chunk).await?;
+            all_data.extend_from_slice(&chunk);
+            start += max_chunk;
+            
+            if (i + 1) % 10 == 0 {
+                let elapsed = start_time.elapsed().as_secs_f32();
+                let speed = if elapsed > 0.0 { (all_data.len() as f32 / 1024.0) / elapsed } else { 0.0 };
+                debug!("Progress: {}/{} chunks ({:.1} KB/s)", i + 1, packets, speed);
+        }
+        }
+        
+        if remain > 0 {
+            let chunk = self.read_chunk_pyzk(start, remain).await?;
+            all_data.extend_from_slice(&chunk);
+        }
+        
+        let _ = self.send_command(CMD_FREE_DATA, &[]).await;
+        
+        let elapsed = start_time.elapsed().as_secs_f32();
+        let speed = if elapsed > 0.0 { (all_data.len() as f32 / 1024.0) / elapsed } else { 0.0 };
+        info!("Downloaded {} bytes in {:.1}s ({:.1} KB/s)", all_data.len(), elapsed, speed);
+        
+        let len = all_data.len();
+        Ok((all_data, len))
+    }
+    
+    /// Simple read (direct command)
+    pub(super) async fn read_simple(&mut self, command: u16) -> Result<(Vec<u8>, usize), ZkError> {
+        let (cmd, data) = self.send_command(command, &[]).await?;
+        
+        if cmd == CMD_DATA {
+            return Ok((data.clone(), data.len()));
+        }
+        
+        if cmd == CMD_PREPARE_DATA && data.len() >= 4 {
+            let size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            if size > 0 {
+                return self.read_prepare_data_stream(size).await;
+            }
+        }
+        
+        if cmd == CMD_ACK_OK && !data.is_empty() {
+            return Ok((data.clone(), data.len()));
+        }
+        
+        Ok((Vec::new(), 0))
+    }
+}
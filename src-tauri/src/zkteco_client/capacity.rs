@@ -0,0 +1,110 @@
+//! Storage counters from CMD_GET_FREE_SIZES
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::info;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Used/maximum counters for one kind of device storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityUsage {
+    pub used: u32,
+    pub capacity: u32,
+    pub free: u32,
+    pub percent_full: f32,
+}
+
+impl CapacityUsage {
+    pub(super) fn new(used: u32, capacity: u32, free: Option<u32>) -> Self {
+        let free = free.unwrap_or_else(|| capacity.saturating_sub(used));
+        let percent_full = if capacity > 0 { used as f32 * 100.0 / capacity as f32 } else { 0.0 };
+        CapacityUsage { used, capacity, free, percent_full }
+    }
+}
+
+/// Storage report parsed from CMD_GET_FREE_SIZES
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCapacity {
+    pub users: CapacityUsage,
+    pub fingers: CapacityUsage,
+    pub records: CapacityUsage,
+    pub faces: Option<CapacityUsage>,  // Only on face-capable models
+    pub cards: u32,                    // Users with a card assigned
+    pub warnings: Vec<String>,         // Storage at or above CAPACITY_WARN_PERCENT
+}
+
+// Percentage at which a storage area is reported as nearly full
+const CAPACITY_WARN_PERCENT: f32 = 90.0;
+
+impl ZKClient {
+    pub(super) async fn read_sizes(&mut self) -> Result<(u32, u32, u32), ZkError> {
+        let capacity = self.read_capacity().await?;
+        info!("Device: {} users, {} records", capacity.users.used, capacity.records.used);
+        Ok((capacity.users.used, capacity.fingers.used, capacity.records.used))
+    }
+    
+    /// Full storage report (pyzk read_sizes: 20 ints, then 3 face ints on face models)
+    pub(super) async fn read_capacity(&mut self) -> Result<DeviceCapacity, ZkError> {
+        let (cmd, data) = self.send_command(CMD_GET_FREE_SIZES, &[]).await?;
+        
+        if cmd != CMD_ACK_OK {
+            return Err(ZkError::reply("Unexpected free sizes response", cmd));
+        }
+        if data.len() < 80 {
+            return Err(ZkError::ShortPacket(format!("Free sizes response too short: {} bytes", data.len())));
+        }
+        
+        let field = |i: usize| i32::from_le_bytes([data[i * 4], data[i * 4 + 1], data[i * 4 + 2], data[i * 4 + 3]]).max(0) as u32;
+        
+        let users = CapacityUsage::new(field(4), field(15), Some(field(18)));
+        let fingers = CapacityUsage::new(field(6), field(14), Some(field(17)));
+        let records = CapacityUsage::new(field(8), field(16), Some(field(19)));
+        let cards = field(12);
+        
+        let faces = if data.len() >= 92 {
+            let face_field = |i: usize| i32::from_le_bytes([data[80 + i * 4], data[81 + i * 4], data[82 + i * 4], data[83 + i * 4]]).max(0) as u32;
+            Some(CapacityUsage::new(face_field(0), face_field(2), None)).filter(|f| f.capacity > 0)
+        } else {
+            None
+        };
+        
+        let mut warnings = Vec::new();
+        for (label, usage) in [("Users", Some(&users)), ("Fingerprints", Some(&fingers)), ("Records", Some(&records)), ("Faces", faces.as_ref())] {
+            if let Some(usage) = usage {
+                if usage.capacity > 0 && usage.percent_full >= CAPACITY_WARN_PERCENT {
+                    warnings.push(format!("{} storage {:.0}% full ({} of {})", label, usage.percent_full, usage.used, usage.capacity));
+                }
+            }
+        }
+        
+        Ok(DeviceCapacity {
+            users,
+            fingers,
+            records,
+            faces,
+            cards,
+            warnings,
+        })
+    }
+}
+
+/// Read the storage capacity report of a device
+pub async fn get_device_capacity(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<DeviceCapacity, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let capacity = client.read_capacity().await;
+    client.disconnect().await?;
+    capacity
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,29 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::sync::Arc;
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn decodes_free_sizes_with_face_counts() {
+    let mut config = SimConfig { face_capacity: 400, reported_records: Some(95_000), ..device(72, 40) };
+    config.users[0].fingers = vec![0, 6];
+    config.users[0].face = true;
+    config.users[0].card = 5551234;
+    config.users[2].card = 5551236;
+    let sim = Simulator::start(config.clone()).await;
+    let store = settings(&format!("capacity-{}", sim.port));
+    
+    let capacity = get_device_capacity("127.0.0.1", sim.port, None, Arc::clone(&store)).await.unwrap();
+    let usage = |u: &CapacityUsage| (u.used, u.capacity, u.free);
+    assert_eq!(usage(&capacity.users), (3, 3000, 2997));
+    assert_eq!(usage(&capacity.fingers), (2, 3000, 2998));
+    assert_eq!(usage(&capacity.records), (95_000, 100_000, 5_000));
+    assert_eq!(capacity.faces.as_ref().map(usage), Some((1, 400, 399)));
+    assert_eq!(capacity.cards, 2);
+    assert_eq!(capacity.warnings, vec!["Records storage 95% full (95000 of 100000)"]);
+    
+    // Without the 3 extra ints the device has no face storage
+    let plain = Simulator::start(SimConfig { face_capacity: 0, ..config }).await;
+    let capacity = get_device_capacity("127.0.0.1", plain.port, None, store).await.unwrap();
+    assert!(capacity.faces.is_none());
+}
@@ -0,0 +1,101 @@
+//! Fetch-then-clear of the attendance log
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use chrono::Local;
+use log::{info, warn};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// What a verified fetch-then-clear removed from the device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClearReceipt {
+    pub device_info: DeviceInfo,
+    pub device_key: String,
+    pub records_removed: u32,
+    pub first_timestamp: Option<String>,
+    pub last_timestamp: Option<String>,
+    pub records_remaining: Option<u32>,   // Device count after the clear (should be 0; None if unreadable)
+    pub cleared_at: String,
+}
+
+impl ZKClient {
+    async fn clear_attendance(&mut self) -> Result<(), ZkError> {
+        let (cmd, _) = self.send_command(CMD_CLEAR_ATTLOG, &[]).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply("Failed to clear attendance", cmd)) }
+    }
+}
+
+/// Download the attendance log, hand it to `persist`, and only then clear it.
+/// The clear is skipped unless the downloaded count matches the device's record
+/// count and `persist` succeeded. The device stays disabled throughout so no
+/// punch can land between the download and the clear.
+pub async fn fetch_and_clear_attendance<F>(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    settings: Arc<DeviceSettingsStore>,
+    persist: F,
+) -> Result<ClearReceipt, ZkError>
+where
+    F: FnOnce(&str, &[AttendanceRecord]) -> Result<(), String> + Send + 'static,
+{
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    
+    let result: Result<ClearReceipt, ZkError> = async {
+        client.load_settings(&ip, port, &settings).await;
+        let device_info = client.get_device_info().await;
+        let device_key = device_key(&device_info, &ip, port);
+        
+        client.disable_device().await?;
+        
+        let record_count = client.read_capacity().await?.records.used;
+        if record_count == 0 {
+            return Err(ZkError::Other("Device reports no records, nothing cleared".to_string()));
+        }
+        
+        let users = client.get_users().await.unwrap_or_else(|_| Vec::new());
+        let records = client.get_attendance(&users, record_count).await?;
+        if records.len() != record_count as usize {
+            return Err(ZkError::Protocol(format!(
+                "Downloaded {} records but device reports {}, not clearing",
+                records.len(), record_count
+            )));
+        }
+        
+        persist(&device_key, &records)
+            .map_err(|e| ZkError::Other(format!("Records not persisted, not clearing: {}", e)))?;
+        
+        client.clear_attendance().await?;
+        let records_remaining = match client.read_capacity().await {
+            Ok(capacity) => Some(capacity.records.used),
+            Err(e) => {
+                warn!("Could not read the record count after clearing {}: {}", device_key, e);
+                None
+            }
+        };
+        info!("🧹 Cleared {} records from {} ({:?} remaining)", record_count, device_key, records_remaining);
+        
+        Ok(ClearReceipt {
+            device_info,
+            device_key,
+            records_removed: record_count,
+            first_timestamp: records.first().map(|r| r.timestamp.clone()),
+            last_timestamp: records.last().map(|r| r.timestamp.clone()),
+            records_remaining,
+            cleared_at: Local::now().to_rfc3339(),
+        })
+    }.await;
+    
+    // Once the log is cleared the receipt must reach the caller, whatever the disconnect does
+    if let Err(e) = client.disconnect().await {
+        warn!("Failed to disconnect from {}:{}: {}", ip, port, e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests;
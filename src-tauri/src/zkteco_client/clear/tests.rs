@@ -0,0 +1,39 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn clears_log_after_persisting() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let receipt = fetch_and_clear_attendance("127.0.0.1", sim.port, None, settings("clear"), |key, records| {
+        assert_eq!(key, "SIM0000000001");
+        assert_eq!(records.len(), 4);
+        Ok(())
+    }).await.unwrap();
+    
+    assert_eq!(receipt.records_removed, 4);
+    assert_eq!(receipt.records_remaining, Some(0));
+    assert_eq!(sim.punch_count(), 0);
+}
+
+#[tokio::test]
+async fn keeps_log_when_persist_fails() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let result = fetch_and_clear_attendance("127.0.0.1", sim.port, None, settings("keep"), |_, _| {
+        Err("disk full".to_string())
+    }).await;
+    
+    assert!(result.is_err());
+    assert_eq!(sim.punch_count(), 4);
+}
+
+#[tokio::test]
+async fn keeps_log_when_record_count_is_unreadable() {
+    let sim = Simulator::start(SimConfig { sizes_unavailable: true, ..device(72, 40) }).await;
+    let result = fetch_and_clear_attendance("127.0.0.1", sim.port, None, settings("unreadable"), |_, _| {
+        panic!("nothing may be persisted without a record count");
+    }).await;
+    
+    assert_eq!(result.unwrap_err().code(), "unexpected_command");
+    assert_eq!(sim.punch_count(), 4);
+}
@@ -0,0 +1,242 @@
+//! Device clock: reading, setting, fleet sync, and the device time encoding
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, Offset, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use log::{info, warn};
+use tokio::sync::Semaphore;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceClock {
+    pub device_time: String,
+    pub pc_time: String,
+    pub drift_seconds: i64,  // Device clock minus PC clock
+}
+
+/// Outcome of setting one device clock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockSetReceipt {
+    pub drift_before_seconds: Option<i64>,  // None when the old time could not be read
+    pub drift_error: Option<String>,        // Why it could not be read
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockSyncResult {
+    pub ip: String,
+    pub port: u16,
+    pub success: bool,
+    pub drift_before_seconds: Option<i64>,
+    pub drift_error: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Attach a zone to a device wall-clock time (PC zone when None); the offset
+/// is resolved per timestamp so punches on either side of a DST change differ
+pub(crate) fn localize(naive: NaiveDateTime, zone: Option<Tz>) -> DateTime<FixedOffset> {
+    let offset = match zone {
+        Some(zone) => offset_at(&zone, naive),
+        None => offset_at(&Local, naive),
+    };
+    DateTime::from_naive_utc_and_offset(naive - offset, offset)
+}
+
+/// Offset of `zone` at a wall-clock time. Ambiguous times (DST fall-back) take
+/// the first occurrence; times skipped by spring-forward take the offset in force just after,
+/// found an hour later on the wall clock (no zone skips more than that)
+fn offset_at<Z: TimeZone>(zone: &Z, naive: NaiveDateTime) -> FixedOffset {
+    zone.from_local_datetime(&naive).earliest()
+        .or_else(|| zone.from_local_datetime(&(naive + chrono::Duration::hours(1))).earliest())
+        .map(|t| t.offset().fix())
+        .unwrap_or_else(|| zone.offset_from_utc_datetime(&naive).fix())
+}
+
+impl ZKClient {
+    /// Read the device clock and compare it with the PC clock
+    pub(super) async fn get_clock(&mut self) -> Result<DeviceClock, ZkError> {
+        let (cmd, data) = self.send_command(CMD_GET_TIME, &[]).await?;
+        if cmd != CMD_ACK_OK || data.len() < 4 {
+            return Err(ZkError::reply("Failed to read device time", cmd));
+        }
+        
+        let pc_time = Local::now();
+        let raw = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let device_time = Self::decode_time(raw, self.settings.timezone())
+            .ok_or_else(|| ZkError::Protocol(format!("Device clock is not a valid date (raw {})", raw)))?;
+        // Both are absolute instants once the device's zone is applied
+        let drift = device_time.signed_duration_since(pc_time);
+        
+        Ok(DeviceClock {
+            device_time: device_time.to_rfc3339(),
+            pc_time: pc_time.to_rfc3339(),
+            drift_seconds: drift.num_seconds(),
+        })
+    }
+    
+    /// Current wall-clock time in the device's zone
+    fn device_now(&self) -> NaiveDateTime {
+        match self.settings.timezone() {
+            Some(zone) => Utc::now().with_timezone(&zone).naive_local(),
+            None => Local::now().naive_local(),
+        }
+    }
+    
+    async fn set_time(&mut self, time: NaiveDateTime) -> Result<(), ZkError> {
+        let (cmd, _) = self.send_command(CMD_SET_TIME, &Self::encode_time(&time)?.to_le_bytes()).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply("Failed to set device time", cmd)) }
+    }
+    
+    /// Decode ZKTeco timestamp in the device's zone (PC zone when None); None if it is not a real date
+    pub(super) fn decode_time(t: u32, zone: Option<Tz>) -> Option<DateTime<FixedOffset>> {
+        Self::decode_naive_time(t).map(|n| localize(n, zone))
+    }
+    
+    /// Timestamp, date and time strings of a record, plus whether the device time was invalid.
+    /// An invalid time is kept as raw bytes rather than replaced by a made-up one.
+    pub(super) fn record_time(dt: Option<DateTime<FixedOffset>>, raw: &[u8]) -> (String, String, String, bool) {
+        match dt {
+            Some(dt) => (
+                dt.to_rfc3339(),
+                dt.format("%Y-%m-%d").to_string(),
+                dt.format("%H:%M:%S").to_string(),
+                false,
+            ),
+            None => (format!("invalid:{}", to_hex(raw)), String::new(), String::new(), true),
+        }
+    }
+    
+    /// Decode a ZKTeco timestamp to wall-clock time (None for impossible dates like 31 Feb)
+    pub(super) fn decode_naive_time(t: u32) -> Option<NaiveDateTime> {
+        let second = t % 60;
+        let t = t / 60;
+        let minute = t % 60;
+        let t = t / 60;
+        let hour = t % 24;
+        let t = t / 24;
+        let day = (t % 31) + 1;
+        let t = t / 31;
+        let month = (t % 12) + 1;
+        let t = t / 12;
+        let year = (t + 2000) as i32;
+        
+        chrono::NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, second)
+    }
+    
+    /// Encode a timestamp in the device format (inverse of decode_time); the
+    /// format only holds the years 2000-2099
+    pub(super) fn encode_time(t: &NaiveDateTime) -> Result<u32, ZkError> {
+        if !(2000..=2099).contains(&t.year()) {
+            return Err(ZkError::InvalidArgument(format!("Device clocks only hold the years 2000-2099, got {}", t.year())));
+        }
+        Ok(((t.year() as u32 - 2000) * 12 * 31 + (t.month() - 1) * 31 + t.day() - 1) * (24 * 60 * 60)
+            + (t.hour() * 60 + t.minute()) * 60
+            + t.second())
+    }
+    
+    /// Decode the 6-byte (y, m, d, h, m, s) timestamp used in realtime events
+    pub(super) fn decode_time_hex(t: &[u8], zone: Option<Tz>) -> Option<DateTime<FixedOffset>> {
+        chrono::NaiveDate::from_ymd_opt(t[0] as i32 + 2000, t[1] as u32, t[2] as u32)?
+            .and_hms_opt(t[3] as u32, t[4] as u32, t[5] as u32)
+            .map(|n| localize(n, zone))
+    }
+}
+
+/// Read a device's clock and its drift from the PC clock
+pub async fn get_device_time(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<DeviceClock, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let clock = client.get_clock().await;
+    client.disconnect().await?;
+    clock
+}
+
+/// Set a device's clock (current time in the device's zone when `time` is None);
+/// returns the drift before the change
+pub async fn set_device_time(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    time: Option<NaiveDateTime>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<ClockSetReceipt, ZkError> {
+    let ip = ip.to_string();
+    if let Some(time) = &time {
+        ZKClient::encode_time(time)?;
+    }
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let drift = client.get_clock().await.map(|c| c.drift_seconds);
+    let now = client.device_now();
+    let result = client.set_time(time.unwrap_or(now)).await;
+    client.disconnect().await?;
+    result?;
+    
+    match &drift {
+        Ok(drift) => info!("🕒 Clock set on {}:{} (drift was {}s)", ip, port, drift),
+        Err(e) => warn!("🕒 Clock set on {}:{}, but the old time could not be read: {}", ip, port, e),
+    }
+    Ok(ClockSetReceipt {
+        drift_before_seconds: drift.as_ref().ok().copied(),
+        drift_error: drift.err().map(|e| e.to_string()),
+    })
+}
+
+/// Set every device's clock to the PC time, at most `max_concurrent` at a time
+pub async fn sync_device_clocks(
+    targets: Vec<DeviceTarget>,
+    max_concurrent: usize,
+    settings: Arc<DeviceSettingsStore>,
+) -> Vec<ClockSyncResult> {
+    let semaphore = Arc::new(Semaphore::new(max_concurrent.max(1)));
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            let semaphore = Arc::clone(&semaphore);
+            let settings = Arc::clone(&settings);
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                match set_device_time(&target.ip, target.port, target.password, None, settings).await {
+                    Ok(receipt) => ClockSyncResult {
+                        ip: target.ip,
+                        port: target.port,
+                        success: true,
+                        drift_before_seconds: receipt.drift_before_seconds,
+                        drift_error: receipt.drift_error,
+                        error: None,
+                    },
+                    Err(e) => ClockSyncResult {
+                        ip: target.ip,
+                        port: target.port,
+                        success: false,
+                        drift_before_seconds: None,
+                        drift_error: None,
+                        error: Some(e.to_string()),
+                    },
+                }
+            })
+        })
+        .collect();
+    
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match handle.await {
+            Ok(result) => results.push(result),
+            Err(e) => warn!("Clock sync task failed: {}", e),
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests;
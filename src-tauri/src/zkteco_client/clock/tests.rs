@@ -0,0 +1,79 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::sync::Arc;
+use chrono::{Utc, NaiveDate};
+use crate::device_settings::DeviceSettings;
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn timestamps_use_device_timezone() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let store = settings("timezone");
+    store.set("SIM0000000001", DeviceSettings { timezone: Some("Asia/Kolkata".to_string()), ..DeviceSettings::default() }).unwrap();
+    
+    let response = connect_and_fetch_attendance("127.0.0.1", sim.port, None, store).await.unwrap();
+    assert_eq!(response.records[0].timestamp, "2024-01-15T09:00:00+05:30");
+    assert_eq!(response.records[0].time, "09:00:00");
+}
+
+#[test]
+fn device_time_encoding_round_trips() {
+    for (y, mo, d, h, mi, s) in [(2000, 1, 1, 0, 0, 0), (2024, 2, 29, 23, 59, 59), (2031, 12, 31, 12, 30, 5), (2099, 7, 15, 6, 1, 59)] {
+        let time = NaiveDate::from_ymd_opt(y, mo, d).unwrap().and_hms_opt(h, mi, s).unwrap();
+        assert_eq!(ZKClient::decode_naive_time(ZKClient::encode_time(&time).unwrap()), Some(time));
+    }
+    for year in [1999, 2100] {
+        let time = NaiveDate::from_ymd_opt(year, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(ZKClient::encode_time(&time).unwrap_err().code(), "invalid_argument");
+    }
+    // 31 February fits the encoding but is no date
+    let raw = ((24 * 12 + 1) * 31 + 30) * 86_400;
+    assert_eq!(ZKClient::decode_naive_time(raw), None);
+}
+
+#[tokio::test]
+async fn reads_sets_and_syncs_device_clocks() {
+    let zone = chrono_tz::Asia::Kolkata;
+    let now = || Utc::now().with_timezone(&zone).naive_local();
+    let behind = Simulator::start(SimConfig { clock: Some(now() - chrono::Duration::hours(1)), ..device(72, 40) }).await;
+    let ahead = Simulator::start(SimConfig { clock: Some(now() + chrono::Duration::seconds(90)), ..device(72, 40) }).await;
+    let store = settings(&format!("clock-{}", behind.port));
+    store.set("SIM0000000001", DeviceSettings { timezone: Some("Asia/Kolkata".to_string()), ..DeviceSettings::default() }).unwrap();
+    
+    // The simulator clock stands still, so allow for the time the test takes
+    let clock = get_device_time("127.0.0.1", behind.port, None, Arc::clone(&store)).await.unwrap();
+    assert!((clock.drift_seconds + 3600).abs() <= 2, "drift {}", clock.drift_seconds);
+    assert!(clock.device_time.ends_with("+05:30"), "{}", clock.device_time);
+    
+    let wanted = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap().and_hms_opt(23, 59, 58).unwrap();
+    set_device_time("127.0.0.1", behind.port, None, Some(wanted), Arc::clone(&store)).await.unwrap();
+    assert_eq!(behind.clock(), Some(wanted));
+    
+    let targets = [&behind, &ahead].map(|sim| DeviceTarget { ip: "127.0.0.1".to_string(), port: sim.port, password: None });
+    let results = sync_device_clocks(targets.to_vec(), 1, Arc::clone(&store)).await;
+    assert!(results.iter().all(|r| r.success), "{:?}", results);
+    assert!(results[1].drift_before_seconds.unwrap().abs_diff(90) <= 2, "{:?}", results[1]);
+    for sim in [&behind, &ahead] {
+        let set_to = sim.clock().unwrap();
+        assert!((set_to - now()).num_seconds().abs() <= 2, "{} vs {}", set_to, now());
+    }
+}
+
+#[tokio::test]
+async fn unreadable_clock_reports_no_drift() {
+    let sim = Simulator::start(SimConfig { clock_unreadable: true, ..device(72, 40) }).await;
+    let store = settings(&format!("clock-unreadable-{}", sim.port));
+    
+    let receipt = set_device_time("127.0.0.1", sim.port, None, None, Arc::clone(&store)).await.unwrap();
+    assert_eq!(receipt.drift_before_seconds, None);
+    assert!(receipt.drift_error.is_some());
+    assert!(sim.clock().is_some());
+    
+    let target = DeviceTarget { ip: "127.0.0.1".to_string(), port: sim.port, password: None };
+    let results = sync_device_clocks(vec![target], 4, Arc::clone(&store)).await;
+    assert!(results[0].success && results[0].drift_before_seconds.is_none() && results[0].drift_error.is_some());
+    
+    let out_of_range = NaiveDate::from_ymd_opt(2100, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+    let err = set_device_time("127.0.0.1", sim.port, None, Some(out_of_range), store).await.unwrap_err();
+    assert_eq!(err.code(), "invalid_argument");
+}
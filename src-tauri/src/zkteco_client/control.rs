@@ -0,0 +1,133 @@
+//! Restart, power off, door unlock and other one-shot device actions
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use chrono::Local;
+use log::info;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// An action performed on a terminal by `control_device` or `signal_device`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeviceAction {
+    Restart,
+    PowerOff,
+    UnlockDoor { seconds: u32 },
+    TestVoice { index: u32 },  // Built-in prompt number ("Thank you" = 0)
+    RefreshData,
+}
+
+impl DeviceAction {
+    /// Actions that take the device offline or open a door; these go through
+    /// `control_device` with `confirm: true`, the rest through `signal_device`
+    pub fn requires_confirmation(&self) -> bool {
+        matches!(self, DeviceAction::Restart | DeviceAction::PowerOff | DeviceAction::UnlockDoor { .. })
+    }
+    
+    /// The device drops the connection after these instead of waiting for CMD_EXIT
+    fn ends_session(&self) -> bool {
+        matches!(self, DeviceAction::Restart | DeviceAction::PowerOff)
+    }
+}
+
+/// Maximum door unlock duration accepted by `DeviceAction::UnlockDoor`
+pub const MAX_UNLOCK_SECONDS: u32 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlReceipt {
+    pub device_info: DeviceInfo,
+    pub device_key: String,
+    pub action: DeviceAction,
+    pub executed_at: String,
+}
+
+impl ZKClient {
+    pub(super) async fn refresh_data(&mut self) -> Result<(), ZkError> {
+        let (cmd, _) = self.send_command(CMD_REFRESHDATA, &[]).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply("Failed to refresh data", cmd)) }
+    }
+    
+    /// Send a control command; Restart/PowerOff leave the session unusable
+    pub(super) async fn control(&mut self, action: &DeviceAction) -> Result<(), ZkError> {
+        let (command, data, context) = match action {
+            DeviceAction::Restart => (CMD_RESTART, Vec::new(), "Failed to restart device"),
+            DeviceAction::PowerOff => (CMD_POWEROFF, Vec::new(), "Failed to power off device"),
+            DeviceAction::UnlockDoor { seconds } => (CMD_UNLOCK, (seconds * 10).to_le_bytes().to_vec(), "Failed to unlock door"),
+            DeviceAction::TestVoice { index } => (CMD_TESTVOICE, index.to_le_bytes().to_vec(), "Failed to play voice"),
+            DeviceAction::RefreshData => return self.refresh_data().await,
+        };
+        let (cmd, _) = self.send_command(command, &data).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply(context, cmd)) }
+    }
+}
+
+/// Restart, power off or unlock a device; refused unless `confirm` is set
+pub async fn control_device(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    action: DeviceAction,
+    confirm: bool,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<ControlReceipt, ZkError> {
+    if !action.requires_confirmation() {
+        return Err(ZkError::InvalidArgument(format!("{:?} needs no confirmation, use signal_device", action)));
+    }
+    if !confirm {
+        return Err(ZkError::InvalidArgument(format!("{:?} must be confirmed", action)));
+    }
+    run_device_action(ip, port, password, action, settings).await
+}
+
+/// Play a voice prompt on or refresh a device; actions that need confirming are refused
+pub async fn signal_device(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    action: DeviceAction,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<ControlReceipt, ZkError> {
+    if action.requires_confirmation() {
+        return Err(ZkError::InvalidArgument(format!("{:?} must be confirmed, use control_device", action)));
+    }
+    run_device_action(ip, port, password, action, settings).await
+}
+
+async fn run_device_action(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    action: DeviceAction,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<ControlReceipt, ZkError> {
+    if let DeviceAction::UnlockDoor { seconds } = action {
+        if !(1..=MAX_UNLOCK_SECONDS).contains(&seconds) {
+            return Err(ZkError::InvalidArgument(format!("Unlock duration must be 1-{} seconds, got {}", MAX_UNLOCK_SECONDS, seconds)));
+        }
+    }
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let device_info = client.get_device_info().await;
+    let device_key = device_key(&device_info, &ip, port);
+    
+    let result = client.control(&action).await;
+    if !action.ends_session() || result.is_err() {
+        client.disconnect().await?;
+    }
+    result?;
+    info!("🎛️ {:?} sent to {}", action, device_key);
+    
+    Ok(ControlReceipt {
+        device_info,
+        device_key,
+        action,
+        executed_at: Local::now().to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,21 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::zk_simulator::Simulator;
+
+#[tokio::test]
+async fn control_actions_need_confirmation() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let unlock = DeviceAction::UnlockDoor { seconds: 5 };
+    
+    let err = control_device("127.0.0.1", sim.port, None, unlock.clone(), false, settings("control")).await.unwrap_err();
+    assert_eq!(err.code(), "invalid_argument");
+    assert!(sim.controls().is_empty());
+    
+    control_device("127.0.0.1", sim.port, None, unlock, true, settings("control")).await.unwrap();
+    signal_device("127.0.0.1", sim.port, None, DeviceAction::TestVoice { index: 3 }, settings("control")).await.unwrap();
+    let err = signal_device("127.0.0.1", sim.port, None, DeviceAction::PowerOff, settings("control")).await.unwrap_err();
+    assert_eq!(err.code(), "invalid_argument");
+    let receipt = control_device("127.0.0.1", sim.port, None, DeviceAction::Restart, true, settings("control")).await.unwrap();
+    assert_eq!(receipt.device_key, "SIM0000000001");
+    assert_eq!(sim.controls(), vec![(CMD_UNLOCK, 50), (CMD_TESTVOICE, 3), (CMD_RESTART, 0)]);
+}
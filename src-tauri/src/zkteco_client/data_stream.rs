@@ -0,0 +1,164 @@
+//! Data packet streams announced by CMD_PREPARE_DATA, in both directions
+
+use log::info;
+use crate::zk_error::ZkError;
+use super::*;
+
+impl ZKClient {
+    /// Read a single chunk of data
+    pub(super) async fn read_chunk_pyzk(&mut self, start: usize, size: usize) -> Result<Vec<u8>, ZkError> {
+        let mut cmd_string = Vec::with_capacity(8);
+        cmd_string.extend_from_slice(&(start as i32).to_le_bytes());
+        cmd_string.extend_from_slice(&(size as i32).to_le_bytes());
+        
+        let buf = self.create_header(CMD_DATA_RDY, &cmd_string);
+        self.transport.send(&buf).await?;
+        
+        let packet_data = self.transport.recv().await?;
+        if packet_data.len() < 8 {
+            return Err(ZkError::ShortPacket(format!("Packet too small: {}", packet_data.len())));
+        }
+        
+        let response_cmd = u16::from_le_bytes([packet_data[0], packet_data[1]]);
+        self.reply_id = u16::from_le_bytes([packet_data[6], packet_data[7]]);
+        let zk_data = if packet_data.len() > 8 { &packet_data[8..] } else { &[] as &[u8] };
+        
+        // Handle ACK_OK - read next packet for data
+        if response_cmd == CMD_ACK_OK {
+            if let Ok(next_packet) = self.transport.recv().await {
+                if next_packet.len() >= 8 {
+                    let next_cmd = u16::from_le_bytes([next_packet[0], next_packet[1]]);
+                    self.reply_id = u16::from_le_bytes([next_packet[6], next_packet[7]]);
+                    
+                    if next_cmd == CMD_DATA && next_packet.len() > 8 {
+                        let mut result = next_packet[8..].to_vec();
+                        self.read_data_tail(&mut result, size).await?;
+                        return Ok(result[..size.min(result.len())].to_vec());
+                    }
+                    
+                    if next_cmd == CMD_PREPARE_DATA {
+                        let inner_size = if next_packet.len() >= 12 {
+                            u32::from_le_bytes([next_packet[8], next_packet[9], next_packet[10], next_packet[11]]) as usize
+                        } else { size };
+                        
+                        let all_data = self.read_data_packets(inner_size).await?;
+                        return Ok(all_data[..size.min(all_data.len())].to_vec());
+                    }
+                }
+            }
+            return Ok(Vec::new());
+        }
+        
+        if response_cmd == CMD_DATA {
+            let mut result = zk_data.to_vec();
+            self.read_data_tail(&mut result, size).await?;
+            if self.transport.is_tcp() {
+                let _ = self.try_read_ack().await;
+            }
+            return Ok(result[..size.min(result.len())].to_vec());
+        }
+        
+        if response_cmd == CMD_PREPARE_DATA {
+            if zk_data.len() < 4 {
+                return Err(ZkError::ShortPacket("PREPARE_DATA: no size".to_string()));
+            }
+            let inner_size = u32::from_le_bytes([zk_data[0], zk_data[1], zk_data[2], zk_data[3]]) as usize;
+            let all_data = self.read_data_packets(inner_size).await?;
+            return Ok(all_data[..size.min(all_data.len())].to_vec());
+        }
+        
+        Err(ZkError::reply("Unexpected response", response_cmd))
+    }
+    
+    /// Complete a CMD_DATA payload that arrived shorter than requested.
+    /// TCP: the rest is still in the current frame. UDP: it follows as more CMD_DATA datagrams.
+    async fn read_data_tail(&mut self, result: &mut Vec<u8>, size: usize) -> Result<(), ZkError> {
+        if result.len() >= size {
+            return Ok(());
+        }
+        
+        if self.transport.is_tcp() {
+            let mut more = vec![0u8; size - result.len()];
+            self.transport.read_exact_raw(&mut more).await?;
+            result.extend_from_slice(&more);
+        } else {
+            let more = self.read_data_packets(size - result.len()).await?;
+            result.extend_from_slice(&more);
+        }
+        Ok(())
+    }
+    
+    /// Collect CMD_DATA packets until `size` bytes arrived or the device sends anything else
+    async fn read_data_packets(&mut self, size: usize) -> Result<Vec<u8>, ZkError> {
+        let mut all_data = Vec::with_capacity(size);
+        
+        while all_data.len() < size {
+            let packet = self.transport.recv().await?;
+            if packet.len() < 8 { continue; }
+            
+            let cmd = u16::from_le_bytes([packet[0], packet[1]]);
+            self.reply_id = u16::from_le_bytes([packet[6], packet[7]]);
+            
+            if cmd == CMD_DATA && packet.len() > 8 {
+                all_data.extend_from_slice(&packet[8..]);
+            } else {
+                // CMD_ACK_OK ends the stream, anything else is unexpected
+                break;
+            }
+        }
+        
+        Ok(all_data)
+    }
+    
+    /// Try to read a trailing ACK packet
+    async fn try_read_ack(&mut self) -> Result<(), ZkError> {
+        let old_timeout = self.transport.read_timeout();
+        let _ = self.transport.set_read_timeout(Some(std::time::Duration::from_millis(100)));
+        if let Ok(packet) = self.transport.recv().await {
+            if packet.len() >= 8 {
+                self.reply_id = u16::from_le_bytes([packet[6], packet[7]]);
+            }
+        }
+        let _ = self.transport.set_read_timeout(old_timeout);
+        Ok(())
+    }
+    
+    /// Read data stream after PREPARE_DATA response
+    pub(super) async fn read_prepare_data_stream(&mut self, size: usize) -> Result<(Vec<u8>, usize), ZkError> {
+        let start_time = std::time::Instant::now();
+        
+        let all_data = self.read_data_packets(size).await?;
+        // A complete stream is closed by an ACK_OK; take it before the next command
+        if all_data.len() >= size {
+            let _ = self.try_read_ack().await;
+        }
+        
+        let _ = self.send_command(CMD_FREE_DATA, &[]).await;
+        
+        let elapsed = start_time.elapsed().as_secs_f32();
+        let speed = if elapsed > 0.0 { (all_data.len() as f32 / 1024.0) / elapsed } else { 0.0 };
+        info!("Downloaded {} bytes in {:.1}s ({:.1} KB/s)", all_data.len(), elapsed, speed);
+        
+        let len = all_data.len();
+        Ok((all_data, len))
+    }
+    
+    /// Upload a buffer with CMD_PREPARE_DATA + CMD_DATA chunks (pyzk _send_with_buffer)
+    pub(super) async fn send_with_buffer(&mut self, buffer: &[u8]) -> Result<(), ZkError> {
+        const MAX_CHUNK: usize = 1024;
+        
+        let _ = self.send_command(CMD_FREE_DATA, &[]).await;
+        let (cmd, _) = self.send_command(CMD_PREPARE_DATA, &(buffer.len() as u32).to_le_bytes()).await?;
+        if cmd != CMD_ACK_OK {
+            return Err(ZkError::reply("Device refused data upload", cmd));
+        }
+        
+        for chunk in buffer.chunks(MAX_CHUNK) {
+            let (cmd, _) = self.send_command(CMD_DATA, chunk).await?;
+            if cmd != CMD_ACK_OK {
+                return Err(ZkError::reply("Device rejected data chunk", cmd));
+            }
+        }
+        Ok(())
+    }
+}
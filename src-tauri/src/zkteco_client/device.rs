@@ -0,0 +1,171 @@
+//! Device identity (name, firmware, serial) and quick reachability checks
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use log::{info, warn};
+use crate::zk_error::ZkError;
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub device_name: String,
+    pub firmware_version: String,
+    pub serial_number: String,
+    pub platform: String,
+    pub mac_address: String,
+    #[serde(default)]
+    pub clock_drift_seconds: Option<i64>,  // Device clock minus PC clock
+    #[serde(default)]
+    pub capacity: Option<DeviceCapacity>,
+}
+
+impl ZKClient {
+    /// Get device information (name, firmware, serial, etc.)
+    /// Get firmware version using direct command
+    async fn get_firmware_version(&mut self) -> String {
+        // Try direct version command first (CMD_VERSION = 1100)
+        if let Ok((cmd, data)) = self.send_command(CMD_VERSION, &[]).await {
+            if cmd == CMD_ACK_OK && !data.is_empty() {
+                let version = String::from_utf8_lossy(&data).trim_end_matches('\0').to_string();
+                if !version.is_empty() {
+                    return version;
+                }
+            }
+        }
+        
+        // Fallback to options
+        let options = ["~ZKFPVersion", "FWVersion", "~FWVersion", "ZKFPVersion"];
+        for opt in options {
+            let v = self.get_option(opt).await.unwrap_or_default();
+            if !v.is_empty() { return v; }
+        }
+        String::new()
+    }
+    
+    /// Get serial number using direct command or options
+    pub(super) async fn get_serial_number(&mut self) -> String {
+        // Try direct serial number command (CMD_SERIALNUMBER = 1101)
+        if let Ok((cmd, data)) = self.send_command(CMD_SERIALNUMBER, &[]).await {
+            if cmd == CMD_ACK_OK && !data.is_empty() {
+                let serial = String::from_utf8_lossy(&data).trim_end_matches('\0').to_string();
+                if !serial.is_empty() {
+                    return serial;
+                }
+            }
+        }
+        
+        // Fallback to options
+        let options = ["~SerialNumber", "SerialNumber", "SN"];
+        for opt in options {
+            let v = self.get_option(opt).await.unwrap_or_default();
+            if !v.is_empty() { return v; }
+        }
+        String::new()
+    }
+    
+    pub(super) async fn get_device_info(&mut self) -> DeviceInfo {
+        let device_name = self.get_option("~DeviceName").await.unwrap_or_default();
+        let firmware_version = self.get_firmware_version().await;
+        let serial_number = self.get_serial_number().await;
+        let platform = self.get_option("~Platform").await.unwrap_or_default();
+        let mac_address = self.get_option("MAC").await.unwrap_or_default();
+        let clock_drift_seconds = self.get_clock().await.ok().map(|c| c.drift_seconds);
+        let capacity = self.read_capacity().await.ok();
+        if let Some(capacity) = &capacity {
+            for warning in &capacity.warnings {
+                warn!("⚠️ {}", warning);
+            }
+        }
+        
+        // Log device info on single line
+        info!("📟 {} | {} | S/N: {} | drift: {}", 
+            if device_name.is_empty() { "Unknown" } else { &device_name },
+            if firmware_version.is_empty() { "-" } else { &firmware_version },
+            if serial_number.is_empty() { "-" } else { &serial_number },
+            clock_drift_seconds.map(|d| format!("{}s", d)).unwrap_or_else(|| "-".to_string())
+        );
+        
+        DeviceInfo {
+            device_name,
+            firmware_version,
+            serial_number,
+            platform,
+            mac_address,
+            clock_drift_seconds,
+            capacity,
+        }
+    }
+}
+
+/// Quick function to get device info without fetching attendance
+/// Used during network scanning
+pub async fn get_device_info_quick(ip: &str, port: u16, password: Option<u32>) -> Result<DeviceInfo, ZkError> {
+    // Quick connect with shorter timeout
+    info!("🔌 Fetching device info from {}:{}", ip, port);
+    
+    let mut client = match ZKClient::open(
+        ip,
+        port,
+        password.unwrap_or(0),
+        Duration::from_secs(5),
+        Duration::from_secs(5),
+    ).await {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("❌ Quick connect failed {}: {}", ip, e);
+            return Err(e);
+        }
+    };
+    
+    // Get device info
+    let device_info = client.get_device_info().await;
+    
+    info!("📟 Scan: {} = {} (S/N: {})", 
+        ip,
+        if device_info.device_name.is_empty() { "Unknown" } else { &device_info.device_name },
+        if device_info.serial_number.is_empty() { "-" } else { &device_info.serial_number }
+    );
+    
+    // Disconnect
+    let _ = client.disconnect().await;
+    
+    Ok(device_info)
+}
+
+/// Probe for a UDP-only terminal with a bare CMD_CONNECT
+/// Used during network scanning when no TCP port answers
+pub async fn probe_udp(ip: &str, port: u16, timeout_ms: u64) -> bool {
+    let socket = match tokio::net::UdpSocket::bind("0.0.0.0:0").await {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    if socket.connect(format!("{}:{}", ip, port)).await.is_err() {
+        return false;
+    }
+    
+    let packet = create_packet(CMD_CONNECT, 0, USHRT_MAX - 1, &[]);
+    if socket.send(&packet).await.is_err() {
+        return false;
+    }
+    
+    let mut buf = [0u8; 1024];
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), socket.recv(&mut buf)).await {
+        Ok(Ok(n)) if n >= 8 => {
+            let cmd = u16::from_le_bytes([buf[0], buf[1]]);
+            if cmd != CMD_ACK_OK && cmd != CMD_ACK_UNAUTH {
+                return false;
+            }
+            
+            // Close the session we just opened so the device doesn't hold it
+            let session_id = u16::from_le_bytes([buf[4], buf[5]]);
+            let reply_id = u16::from_le_bytes([buf[6], buf[7]]);
+            let exit = create_packet(CMD_EXIT, session_id, reply_id, &[]);
+            let _ = socket.send(&exit).await;
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests;
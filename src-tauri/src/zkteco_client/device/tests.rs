@@ -0,0 +1,12 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::zk_simulator::Simulator;
+
+#[tokio::test]
+async fn probe_finds_udp_terminal() {
+    let sim = Simulator::start_udp(device(72, 40)).await;
+    assert!(probe_udp("127.0.0.1", sim.port, 1000).await);
+    
+    let tcp_only = Simulator::start(device(72, 40)).await;
+    assert!(!probe_udp("127.0.0.1", tcp_only.port, 300).await);
+}
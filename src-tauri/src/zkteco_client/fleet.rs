@@ -0,0 +1,124 @@
+//! Attendance downloads from several devices at once
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+use chrono::DateTime;
+use log::{info, warn};
+use tokio::sync::Semaphore;
+use crate::device_settings::DeviceSettingsStore;
+use super::*;
+
+/// Outcome for one device of a fleet fetch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetDeviceResult {
+    pub ip: String,
+    pub port: u16,
+    pub success: bool,
+    pub device_key: Option<String>,
+    pub device_info: Option<DeviceInfo>,
+    pub record_count: usize,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// An attendance record in a fleet timeline, tagged with the device it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetRecord {
+    pub device_serial: String,
+    #[serde(flatten)]
+    pub record: AttendanceRecord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetFetchResponse {
+    pub devices: Vec<FleetDeviceResult>,
+    pub records: Vec<FleetRecord>,   // All devices, oldest first
+}
+
+// Devices fetched at once by a fleet fetch unless the caller says otherwise
+pub const FLEET_DEFAULT_CONCURRENCY: usize = 4;
+
+/// Fetch attendance from several devices, at most `max_concurrent` at a time, and
+/// merge their records into one timeline. A failing device does not stop the others.
+pub async fn fetch_attendance_fleet(
+    targets: Vec<DeviceTarget>,
+    max_concurrent: usize,
+    settings: Arc<DeviceSettingsStore>,
+) -> FleetFetchResponse {
+    let semaphore = Arc::new(Semaphore::new(max_concurrent.max(1)));
+    info!("🚚 Fleet fetch from {} devices ({} at a time)", targets.len(), max_concurrent.max(1));
+    
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            let semaphore = Arc::clone(&semaphore);
+            let settings = Arc::clone(&settings);
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let started = Instant::now();
+                let result = connect_and_fetch_attendance(&target.ip, target.port, target.password, settings).await;
+                (target, result, started.elapsed())
+            })
+        })
+        .collect();
+    
+    let mut devices = Vec::with_capacity(handles.len());
+    let mut records = Vec::new();
+    for handle in handles {
+        let (target, result, elapsed) = match handle.await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                warn!("Fleet fetch task failed: {}", e);
+                continue;
+            }
+        };
+        let duration_ms = elapsed.as_millis() as u64;
+        
+        match result {
+            Ok(response) => {
+                let device_key = device_key(&response.device_info, &target.ip, target.port);
+                info!("🚚 {}:{} ({}): {} records in {}ms", target.ip, target.port, device_key, response.records.len(), duration_ms);
+                devices.push(FleetDeviceResult {
+                    ip: target.ip,
+                    port: target.port,
+                    success: true,
+                    device_key: Some(device_key.clone()),
+                    device_info: Some(response.device_info),
+                    record_count: response.records.len(),
+                    error: None,
+                    duration_ms,
+                });
+                records.extend(response.records.into_iter().map(|record| FleetRecord {
+                    device_serial: device_key.clone(),
+                    record,
+                }));
+            }
+            Err(e) => {
+                warn!("🚚 {}:{} failed after {}ms: {}", target.ip, target.port, duration_ms, e);
+                devices.push(FleetDeviceResult {
+                    ip: target.ip,
+                    port: target.port,
+                    success: false,
+                    device_key: None,
+                    device_info: None,
+                    record_count: 0,
+                    error: Some(e.to_string()),
+                    duration_ms,
+                });
+            }
+        }
+    }
+    
+    // Devices may run in different zones, so order by instant rather than by string;
+    // records with invalid timestamps go last
+    records.sort_by_cached_key(|r| {
+        let instant = DateTime::parse_from_rfc3339(&r.record.timestamp).ok();
+        (instant.is_none(), instant, r.device_serial.clone(), r.record.user_id)
+    });
+    
+    FleetFetchResponse { devices, records }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,35 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::zk_simulator::{SimPunch, Simulator};
+
+#[tokio::test]
+async fn fleet_fetch_merges_devices_and_reports_failures() {
+    let first = Simulator::start(device(72, 40)).await;
+    let mut config = device(28, 16);
+    config.serial_number = "SIM0000000002".to_string();
+    config.punches = vec![SimPunch::new(&config.users[1], at(15, 8, 30), 0)];
+    let second = Simulator::start(config).await;
+    let closed = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    
+    let targets = [first.port, second.port, closed].iter()
+        .map(|&port| DeviceTarget { ip: "127.0.0.1".to_string(), port, password: None })
+        .collect();
+    let response = fetch_attendance_fleet(targets, 2, settings("fleet")).await;
+    
+    let outcomes: Vec<_> = response.devices.iter().map(|d| (d.success, d.record_count)).collect();
+    assert_eq!(outcomes, vec![(true, 4), (true, 1), (false, 0)]);
+    assert!(response.devices[2].error.is_some());
+    
+    let timeline: Vec<_> = response.records.iter()
+        .map(|r| (r.device_serial.as_str(), r.record.time.as_str()))
+        .collect();
+    assert_eq!(&timeline[..3], &[
+        ("SIM0000000002", "08:30:00"),
+        ("SIM0000000001", "09:00:00"),
+        ("SIM0000000001", "09:05:00"),
+    ]);
+    assert_eq!(timeline.len(), 5);
+}
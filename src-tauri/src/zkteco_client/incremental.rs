@@ -0,0 +1,143 @@
+//! Incremental attendance downloads from a stored watermark
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use chrono::{DateTime, FixedOffset};
+use log::{info, warn};
+use crate::device_settings::DeviceSettingsStore;
+use crate::watermark_store::WatermarkStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Position in a device's attendance log up to which records have been fetched
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttendanceWatermark {
+    pub record_index: u32,               // Number of records already seen
+    pub record_size: usize,              // Record layout the index refers to (8/16/40)
+    pub last_timestamp: Option<String>,  // Timestamp of the last seen record, used as an anchor
+    #[serde(default)]
+    pub last_user_id: Option<u32>,       // Its user, so punches in the same second are told apart
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncrementalAttendanceResponse {
+    pub device_info: DeviceInfo,
+    pub device_key: String,          // Serial number, or ip:port when the device has none
+    pub records: Vec<AttendanceRecord>,
+    pub watermark: AttendanceWatermark,
+    pub full_download: bool,         // false when only the tail of the log was transferred
+    #[serde(default)]
+    pub record_layout: Option<RecordLayout>,  // Only for full downloads
+}
+
+impl ZKClient {
+    /// Fetch only records after the watermark when possible.
+    /// The last seen record is re-read as an anchor; if it no longer matches
+    /// (log cleared or rolled over) the whole log is downloaded instead.
+    async fn get_attendance_since(
+        &mut self,
+        users: &[User],
+        expected_records: u32,
+        watermark: Option<&AttendanceWatermark>,
+    ) -> Result<(Vec<AttendanceRecord>, AttendanceWatermark, Option<RecordLayout>), ZkError> {
+        if let Some(mark) = watermark {
+            if mark.record_index > 0 && mark.record_size > 0 && expected_records >= mark.record_index {
+                let anchor_index = (mark.record_index - 1) as usize;
+                let skip = 4 + anchor_index * mark.record_size;
+                info!("Fetching attendance from record {} of {}...", mark.record_index, expected_records);
+                
+                let (tail, _) = self.read_with_buffer_from(CMD_ATTLOG_RRQ, 0, skip).await?;
+                let mut records = Self::parse_attendance(&tail, mark.record_size, users, self.settings.timezone());
+                
+                // Watermarks saved before last_user_id existed anchor on the timestamp alone
+                let anchored = records.first()
+                    .map(|r| Some(&r.timestamp) == mark.last_timestamp.as_ref()
+                        && mark.last_user_id.is_none_or(|id| id == r.user_id))
+                    .unwrap_or(false);
+                if anchored {
+                    let anchor = records.remove(0);
+                    let last = records.last().unwrap_or(&anchor);
+                    let new_mark = AttendanceWatermark {
+                        record_index: mark.record_index + records.len() as u32,
+                        record_size: mark.record_size,
+                        last_timestamp: Some(last.timestamp.clone()),
+                        last_user_id: Some(last.user_id),
+                    };
+                    return Ok((records, new_mark, None));
+                }
+                warn!("Attendance watermark does not match device log, downloading everything");
+            }
+        }
+        
+        let (records, layout) = self.download_attendance(users, Some(expected_records)).await?;
+        let new_mark = AttendanceWatermark {
+            record_index: records.len() as u32,
+            record_size: layout.as_ref().map(|l| l.record_size).unwrap_or(0),
+            last_timestamp: records.last().map(|r| r.timestamp.clone()),
+            last_user_id: records.last().map(|r| r.user_id),
+        };
+        // A fresh full download always comes with a layout, even for an empty log
+        Ok((records, new_mark, Some(layout.unwrap_or(RecordLayout {
+            record_size: 0,
+            confidence: 0.0,
+            overridden: false,
+            candidates: Vec::new(),
+        }))))
+    }
+}
+
+/// Fetch only attendance records newer than the stored watermark (and `since`, if given).
+/// The new watermark is returned, not saved - persist it once the records are stored.
+pub async fn connect_and_fetch_attendance_since(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    since: Option<DateTime<FixedOffset>>,
+    watermarks: Arc<WatermarkStore>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<IncrementalAttendanceResponse, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    
+    client.load_settings(&ip, port, &settings).await;
+    let device_info = client.get_device_info().await;
+    let device_key = device_key(&device_info, &ip, port);
+    
+    if let Err(e) = client.disable_device().await {
+        warn!("Failed to disable device: {}", e);
+    }
+    
+    // Without the count the watermark cannot be checked against the log
+    let (_, _, record_count) = client.read_sizes().await?;
+    let users = client.get_users().await.unwrap_or_else(|_| Vec::new());
+    
+    let previous = watermarks.get(&device_key);
+    let (mut records, watermark, record_layout) =
+        client.get_attendance_since(&users, record_count, previous.as_ref()).await?;
+    let full_download = record_layout.is_some();
+    
+    client.disconnect().await?;
+    
+    if let Some(since) = since {
+        records.retain(|r| {
+            DateTime::parse_from_rfc3339(&r.timestamp)
+                .map(|t| t > since)
+                .unwrap_or(true)
+        });
+    }
+    info!("Fetched {} new attendance records ({})", records.len(),
+        if full_download { "full log" } else { "incremental" });
+    
+    Ok(IncrementalAttendanceResponse {
+        device_info,
+        device_key,
+        records,
+        watermark,
+        full_download,
+        record_layout,
+    })
+}
+
+#[cfg(test)]
+mod tests;
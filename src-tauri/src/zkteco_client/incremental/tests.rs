@@ -0,0 +1,80 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::sync::Arc;
+use crate::watermark_store::WatermarkStore;
+use crate::zk_simulator::{SimConfig, SimPunch, Simulator};
+
+#[tokio::test]
+async fn unreadable_record_count_only_stops_incremental_fetches() {
+    let sim = Simulator::start(SimConfig { sizes_unavailable: true, ..device(72, 40) }).await;
+    let response = fetch(&sim, None).await.unwrap();
+    assert_eq!(response.records.len(), 4);
+    
+    let err = connect_and_fetch_attendance_since("127.0.0.1", sim.port, None, None, watermarks("sizes"), settings("sizes"))
+        .await.unwrap_err();
+    assert_eq!(err.code(), "unexpected_command");
+}
+
+async fn fetch_since(sim: &Simulator, watermarks: &Arc<WatermarkStore>) -> IncrementalAttendanceResponse {
+    let response = connect_and_fetch_attendance_since(
+        "127.0.0.1", sim.port, None, None, Arc::clone(watermarks), settings(&format!("since-{}", sim.port)),
+    ).await.unwrap();
+    watermarks.set(&response.device_key, response.watermark.clone()).unwrap();
+    response
+}
+
+fn watermarks(name: &str) -> Arc<WatermarkStore> {
+    let path = std::env::temp_dir().join(format!("zk-watermarks-{}-{}.json", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    Arc::new(WatermarkStore::load(path))
+}
+
+#[tokio::test]
+async fn incremental_fetch_returns_only_new_punches() {
+    let mut config = device(72, 40);
+    let users = config.users.clone();
+    config.punches.truncate(1);
+    let sim = Simulator::start(config).await;
+    let marks = watermarks("incremental");
+    
+    let first = fetch_since(&sim, &marks).await;
+    assert!(first.full_download);
+    assert_eq!(first.records.len(), 1);
+    
+    // A second user punches in the same second as the watermark record
+    sim.push_punch(SimPunch::new(&users[1], at(15, 9, 0), 0));
+    sim.push_punch(SimPunch::new(&users[2], at(15, 9, 30), 0));
+    let second = fetch_since(&sim, &marks).await;
+    assert!(!second.full_download);
+    let ids: Vec<u32> = second.records.iter().map(|r| r.user_id).collect();
+    assert_eq!(ids, vec![1002, 1003]);
+    assert_eq!(second.watermark.record_index, 3);
+    assert_eq!(second.watermark.last_user_id, Some(1003));
+    
+    let third = fetch_since(&sim, &marks).await;
+    assert!(!third.full_download);
+    assert!(third.records.is_empty());
+}
+
+#[tokio::test]
+async fn same_second_punch_by_another_user_is_not_an_anchor() {
+    let config = device(72, 40);
+    let users = config.users.clone();
+    let sim = Simulator::start(SimConfig {
+        punches: vec![SimPunch::new(&users[0], at(15, 9, 0), 0), SimPunch::new(&users[1], at(15, 9, 0), 0)],
+        ..config
+    }).await;
+    let marks = watermarks("tie");
+    fetch_since(&sim, &marks).await;
+    
+    // Log cleared and refilled: the record at the watermark index has the
+    // watermark's timestamp but belongs to someone else
+    sim.set_punches(vec![
+        SimPunch::new(&users[1], at(15, 9, 0), 0),
+        SimPunch::new(&users[0], at(15, 9, 0), 0),
+        SimPunch::new(&users[2], at(15, 9, 5), 0),
+    ]);
+    let response = fetch_since(&sim, &marks).await;
+    assert!(response.full_download);
+    assert_eq!(response.records.len(), 3);
+}
@@ -0,0 +1,112 @@
+//! Detection of the attendance record layout a device uses
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use chrono::Local;
+use crate::device_settings::RECORD_SIZES;
+use super::*;
+
+/// How well one attendance layout explains the downloaded log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutCandidate {
+    pub record_size: usize,
+    pub records: usize,
+    pub plausible_timestamps: f32,  // Fraction of sampled records with a sane date
+    pub known_users: Option<f32>,   // Fraction matching a device user (None without a user list)
+    pub matches_count: bool,        // Log size / reported record count gives this size
+    pub score: f32,
+}
+
+/// Attendance record layout used for a download and how it was chosen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordLayout {
+    pub record_size: usize,
+    pub confidence: f32,            // 0..1, always 1 for overrides
+    pub overridden: bool,           // Set in device settings rather than detected
+    pub candidates: Vec<LayoutCandidate>,
+}
+
+// Records inspected per candidate when detecting the attendance layout
+const LAYOUT_SAMPLE: usize = 200;
+
+// Below this the detected layout is probably wrong and an override is advised
+pub(super) const LAYOUT_LOW_CONFIDENCE: f32 = 0.6;
+
+impl ZKClient {
+    /// Score each known attendance layout against the log: a right guess decodes
+    /// to sane dates and to ids of users on the device
+    pub(super) fn detect_record_layout(data: &[u8], total_size: usize, expected_records: u32, users: &[User]) -> RecordLayout {
+        let latest = Local::now().naive_local() + chrono::Duration::days(1);
+        let earliest = chrono::NaiveDate::from_ymd_opt(2000, 1, 2).and_then(|d| d.and_hms_opt(0, 0, 0)).unwrap_or_default();
+        let uids: HashSet<String> = users.iter().map(|u| u.uid.to_string()).collect();
+        let user_ids: HashSet<&str> = users.iter().map(|u| u.user_id.as_str()).collect();
+        let reported_size = if expected_records > 0 { total_size / expected_records as usize } else { 0 };
+        
+        let mut candidates: Vec<LayoutCandidate> = RECORD_SIZES.iter().filter_map(|&size| {
+            let count = data.len() / size;
+            if count == 0 {
+                return None;
+            }
+            
+            let (mut sampled, mut plausible, mut known) = (0usize, 0usize, 0usize);
+            for i in (0..count).step_by((count / LAYOUT_SAMPLE).max(1)) {
+                let r = &data[i * size..(i + 1) * size];
+                let u32_at = |o: usize| u32::from_le_bytes([r[o], r[o + 1], r[o + 2], r[o + 3]]);
+                let uid = u16::from_le_bytes([r[0], r[1]]).to_string();
+                let (timestamp, is_known) = match size {
+                    8 => (u32_at(3), uids.contains(&uid)),
+                    16 => (u32_at(4), user_ids.contains(u32_at(0).to_string().as_str())),
+                    _ => (u32_at(27), uids.contains(&uid) || user_ids.contains(decode_str(&r[2..26]).as_str())),
+                };
+                
+                sampled += 1;
+                if Self::decode_naive_time(timestamp).map(|t| t >= earliest && t <= latest).unwrap_or(false) {
+                    plausible += 1;
+                    // Ids alone match by chance too easily (status bytes read as uid 1)
+                    if is_known {
+                        known += 1;
+                    }
+                }
+            }
+            
+            let plausible_timestamps = plausible as f32 / sampled as f32;
+            let known_users = (!users.is_empty()).then(|| known as f32 / sampled as f32);
+            let matches_count = reported_size == size;
+            let mut score = match known_users {
+                Some(known) => 0.6 * plausible_timestamps + 0.3 * known,
+                None => 0.9 * plausible_timestamps,
+            } + if matches_count { 0.1 } else { 0.0 };
+            if !data.len().is_multiple_of(size) {
+                score *= 0.5;
+            }
+            
+            Some(LayoutCandidate {
+                record_size: size,
+                records: count,
+                plausible_timestamps,
+                known_users,
+                matches_count,
+                score,
+            })
+        }).collect();
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        
+        // Confidence blends how good the best layout is with how far it beats the next one
+        let best = candidates.first().map(|c| c.score).unwrap_or(0.0);
+        let runner_up = candidates.get(1).map(|c| c.score).unwrap_or(0.0);
+        let record_size = candidates.first()
+            .map(|c| c.record_size)
+            .or_else(|| RECORD_SIZES.contains(&reported_size).then_some(reported_size))
+            .unwrap_or(40);
+        
+        RecordLayout {
+            record_size,
+            confidence: ((best + (best - runner_up)) / 2.0).clamp(0.0, 1.0),
+            overridden: false,
+            candidates,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,30 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::device_settings::DeviceSettings;
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn detects_layout_when_record_count_is_wrong() {
+    // Size / count would say 8 bytes; the content says 16
+    let sim = Simulator::start(SimConfig { reported_records: Some(8), ..device(28, 16) }).await;
+    let response = fetch(&sim, None).await.unwrap();
+    
+    let layout = response.record_layout.unwrap();
+    assert_eq!(layout.record_size, 16);
+    assert!(layout.confidence >= LAYOUT_LOW_CONFIDENCE);
+    assert_eq!(response.records.len(), 4);
+    assert_eq!(response.records[3].user_name, "Chitra");
+}
+
+#[tokio::test]
+async fn record_layout_override_is_used() {
+    let sim = Simulator::start(device(28, 16)).await;
+    let store = settings("override");
+    store.set("SIM0000000001", DeviceSettings { record_size: Some(16), user_size: Some(28), ..DeviceSettings::default() }).unwrap();
+    
+    let response = connect_and_fetch_attendance("127.0.0.1", sim.port, None, store).await.unwrap();
+    let layout = response.record_layout.unwrap();
+    assert!(layout.overridden);
+    assert_eq!(layout.record_size, 16);
+    assert_eq!(response.records[0].user_name, "Alice");
+}
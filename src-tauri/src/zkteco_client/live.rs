@@ -0,0 +1,243 @@
+//! Live capture of punches pushed by the device (CMD_REG_EVENT)
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono_tz::Tz;
+use log::{debug, info, warn};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// A punch pushed by the device during live capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LivePunchEvent {
+    pub ip: String,
+    pub port: u16,
+    pub device_key: String,
+    pub record: AttendanceRecord,
+}
+
+/// Connection state changes of a live capture session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveStatusEvent {
+    pub ip: String,
+    pub port: u16,
+    pub connected: bool,
+    pub message: String,
+}
+
+pub enum LiveCaptureEvent {
+    Punch(LivePunchEvent),
+    Status(LiveStatusEvent),
+}
+
+// Realtime event flags
+pub(super) const EF_ATTLOG: u32 = 1;
+
+// Live capture tuning
+const LIVE_READ_TIMEOUT: Duration = Duration::from_secs(1);   // How often the stop flag is checked
+const LIVE_IDLE_PROBE: Duration = Duration::from_secs(60);    // Probe the session after this much silence
+const LIVE_PROBE_TIMEOUT: Duration = Duration::from_secs(10); // No answer to the probe means the link is dead
+const LIVE_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+impl ZKClient {
+    /// Register for realtime events (EF_ATTLOG etc.), 0 to unregister
+    async fn reg_event(&mut self, flags: u32) -> Result<(), ZkError> {
+        let (cmd, _) = self.send_command(CMD_REG_EVENT, &flags.to_le_bytes()).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply("Failed to register events", cmd)) }
+    }
+    
+    /// Prepare the device for live capture (matching pyzk live_capture)
+    pub(super) async fn start_live_capture(&mut self) -> Result<(), ZkError> {
+        let _ = self.send_command(CMD_CANCELCAPTURE, &[]).await;
+        let _ = self.send_command(CMD_STARTVERIFY, &[]).await;
+        self.enable_device().await?;
+        self.reg_event(EF_ATTLOG).await?;
+        self.transport.set_read_timeout(Some(LIVE_READ_TIMEOUT))
+    }
+    
+    /// Re-send the event registration without waiting; the device's ACK shows the link is alive
+    async fn send_live_probe(&mut self) -> Result<(), ZkError> {
+        let buf = self.create_header(CMD_REG_EVENT, &EF_ATTLOG.to_le_bytes());
+        self.transport.send(&buf).await
+    }
+    
+    /// Acknowledge a pushed event packet (the device expects no reply)
+    async fn ack_ok(&mut self) -> Result<(), ZkError> {
+        let buf = create_packet(CMD_ACK_OK, self.session_id, USHRT_MAX - 1, &[]);
+        self.transport.send(&buf).await
+    }
+    
+    /// Wait for the next packet during live capture.
+    /// Ok(None) when nothing arrived within the read timeout.
+    async fn recv_live_punches(&mut self, user_lookup: &HashMap<String, String>) -> Result<Option<Vec<AttendanceRecord>>, ZkError> {
+        let packet = match self.transport.recv().await {
+            Ok(p) => p,
+            Err(e) if e.is_timeout() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        
+        if packet.len() < 8 {
+            return Ok(Some(Vec::new()));
+        }
+        
+        let cmd = u16::from_le_bytes([packet[0], packet[1]]);
+        if cmd != CMD_REG_EVENT {
+            // e.g. the ACK to a liveness probe
+            return Ok(Some(Vec::new()));
+        }
+        
+        self.ack_ok().await?;
+        Ok(Some(Self::parse_live_punches(&packet[8..], user_lookup, self.settings.timezone())))
+    }
+    
+    /// Parse EF_ATTLOG event payloads (layouts from pyzk live_capture)
+    fn parse_live_punches(mut data: &[u8], user_lookup: &HashMap<String, String>, zone: Option<Tz>) -> Vec<AttendanceRecord> {
+        let mut records = Vec::new();
+        
+        while data.len() >= 10 {
+            let (user_id_str, rest, size) = match data.len() {
+                // user_id(H), status, punch, time(6)
+                10 | 14 => (u16::from_le_bytes([data[0], data[1]]).to_string(), &data[2..], if data.len() == 10 { 10 } else { 14 }),
+                // user_id(I), status, punch, time(6)
+                12 => (u32::from_le_bytes([data[0], data[1], data[2], data[3]]).to_string(), &data[4..], 12),
+                // user_id(24s), status, punch, time(6) [+ 4 or 20 bytes]
+                n if n >= 32 => {
+                    let size = if n >= 52 { 52 } else if n >= 36 { 36 } else { 32 };
+                    let id = String::from_utf8_lossy(&data[..24]).split('\0').next().unwrap_or("").trim().to_string();
+                    (id, &data[24..], size)
+                }
+                n => {
+                    debug!("Unknown realtime event layout ({} bytes)", n);
+                    break;
+                }
+            };
+            
+            let status = rest[0];
+            let punch = rest[1];
+            let (timestamp, date, time, timestamp_invalid) =
+                Self::record_time(Self::decode_time_hex(&rest[2..8], zone), &rest[2..8]);
+            
+            let user_name = user_lookup
+                .get(&user_id_str)
+                .cloned()
+                .unwrap_or_else(|| format!("ID: {}", user_id_str));
+            
+            records.push(AttendanceRecord {
+                user_id: user_id_str.parse().unwrap_or(0),
+                user_name,
+                timestamp,
+                status,
+                punch,
+                date,
+                time,
+                verify: VerifyMethod::from_raw(status),
+                punch_state: PunchState::from_raw(punch),
+                workcode: 0,
+                timestamp_invalid,
+            });
+            
+            data = &data[size..];
+        }
+        
+        records
+    }
+}
+
+/// Stream punches from a device as they happen until `stop` is set.
+/// Reconnects with backoff whenever the session drops.
+pub async fn live_capture<F>(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    stop: Arc<AtomicBool>,
+    settings: Arc<DeviceSettingsStore>,
+    mut on_event: F,
+) -> Result<(), ZkError>
+where
+    F: FnMut(LiveCaptureEvent) + Send + 'static,
+{
+    let ip = ip.to_string();
+    
+    let status = |connected: bool, message: String| LiveCaptureEvent::Status(LiveStatusEvent {
+        ip: ip.clone(),
+        port,
+        connected,
+        message,
+    });
+    let mut backoff = Duration::from_secs(2);
+    
+    while !stop.load(Ordering::Relaxed) {
+        let result: Result<(), ZkError> = async {
+            let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+            let device_key = client.load_settings(&ip, port, &settings).await;
+            let users = client.get_users().await.unwrap_or_else(|_| Vec::new());
+            let user_lookup = ZKClient::build_user_lookup(&users);
+            client.start_live_capture().await?;
+            
+            info!("📡 Live capture started on {}:{}", ip, port);
+            on_event(status(true, "Live capture started".to_string()));
+            backoff = Duration::from_secs(2);
+            
+            let mut last_rx = Instant::now();
+            let mut probe_sent: Option<Instant> = None;
+            
+            while !stop.load(Ordering::Relaxed) {
+                match client.recv_live_punches(&user_lookup).await? {
+                    Some(records) => {
+                        last_rx = Instant::now();
+                        probe_sent = None;
+                        for record in records {
+                            info!("👆 Live punch {}:{} user={} at {}", ip, port, record.user_id, record.time);
+                            on_event(LiveCaptureEvent::Punch(LivePunchEvent {
+                                ip: ip.clone(),
+                                port,
+                                device_key: device_key.clone(),
+                                record,
+                            }));
+                        }
+                    }
+                    None => match probe_sent {
+                        Some(sent) if sent.elapsed() > LIVE_PROBE_TIMEOUT => {
+                            return Err(ZkError::Timeout("Device stopped responding".to_string()));
+                        }
+                        None if last_rx.elapsed() > LIVE_IDLE_PROBE => {
+                            client.send_live_probe().await?;
+                            probe_sent = Some(Instant::now());
+                        }
+                        _ => {}
+                    },
+                }
+            }
+            
+            let _ = client.transport.set_read_timeout(Some(Duration::from_secs(5)));
+            let _ = client.reg_event(0).await;
+            client.disconnect().await
+        }.await;
+        
+        if let Err(e) = result {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            warn!("📡 Live capture on {}:{} interrupted: {} (retrying in {}s)", ip, port, e, backoff.as_secs());
+            on_event(status(false, e.to_string()));
+            
+            // Sleep in small steps so stop requests are honoured quickly
+            let resume_at = Instant::now() + backoff;
+            while Instant::now() < resume_at && !stop.load(Ordering::Relaxed) {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            }
+            backoff = (backoff * 2).min(LIVE_MAX_BACKOFF);
+        }
+    }
+    
+    info!("📡 Live capture stopped on {}:{}", ip, port);
+    on_event(status(false, "Live capture stopped".to_string()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
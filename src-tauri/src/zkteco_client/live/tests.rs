@@ -0,0 +1,55 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::zk_simulator::{SimPunch, Simulator};
+
+async fn next_live_event(rx: &mut tokio::sync::mpsc::UnboundedReceiver<LiveCaptureEvent>) -> LiveCaptureEvent {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn live_capture_reports_pushed_punches_in_each_event_layout() {
+    for (user_size, record_size) in [(28, 8), (28, 16), (72, 40)] {
+        let config = device(user_size, record_size);
+        let users = config.users.clone();
+        let sim = Simulator::start(config).await;
+        let stop = Arc::new(AtomicBool::new(false));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn(live_capture(
+            "127.0.0.1", sim.port, None, Arc::clone(&stop), settings(&format!("live-{}", sim.port)),
+            move |event| { let _ = tx.send(event); },
+        ));
+        
+        match next_live_event(&mut rx).await {
+            LiveCaptureEvent::Status(status) => assert!(status.connected, "{}", status.message),
+            LiveCaptureEvent::Punch(_) => panic!("punch before capture started"),
+        }
+        
+        sim.punch_live(SimPunch::new(&users[2], at(20, 8, 30), 0));
+        sim.punch_live(SimPunch { status: 15, ..SimPunch::new(&users[0], at(20, 17, 45), 1) });
+        let mut punches = Vec::new();
+        while punches.len() < 2 {
+            match next_live_event(&mut rx).await {
+                LiveCaptureEvent::Punch(event) => punches.push(event.record),
+                LiveCaptureEvent::Status(status) => panic!("{}-byte records: {}", record_size, status.message),
+            }
+        }
+        
+        assert_eq!(punches[0].user_id, 1003, "{}-byte records", record_size);
+        assert_eq!(punches[0].user_name, "Chitra");
+        assert_eq!(punches[0].date, "2024-01-20");
+        assert_eq!(punches[0].time, "08:30:00");
+        assert_eq!(punches[0].punch_state, PunchState::CheckIn);
+        assert_eq!(punches[1].user_id, 1001);
+        assert_eq!(punches[1].user_name, "Alice");
+        assert_eq!(punches[1].time, "17:45:00");
+        assert_eq!(punches[1].status, 15);
+        assert_eq!(punches[1].punch_state, PunchState::CheckOut);
+        assert_eq!(sim.punch_count(), 6);
+        
+        stop.store(true, Ordering::Relaxed);
+        task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! Reading and writing device options
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::{info, warn};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Options read by `dump_device_options`. Keys starting with `~` are read-only.
+pub const KNOWN_OPTIONS: &[&str] = &[
+    "~DeviceName", "~SerialNumber", "~Platform", "~OS", "~OEMVendor", "~ProductTime",
+    "~ZKFPVersion", "~ZKFaceVersion", "~UserExtFmt", "~ExtendFmt", "~PIN2Width",
+    "~MaxUserCount", "~MaxAttLogCount", "~MaxFingerCount", "~IsOnlyRFMachine", "~RFCardOn",
+    "MAC", "IPAddress", "NetMask", "GATEIPAddress", "DeviceID", "COMKey",
+    "LockOn", "DoorSensorMode", "VerifyMode", "WorkCode", "FaceFunOn", "VOLUME", "Language", "DtFmt",
+];
+
+/// An option as read from a device (`None` = the device does not support it)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceOption {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceOptionsReport {
+    pub device_info: DeviceInfo,
+    pub device_key: String,
+    pub options: Vec<DeviceOption>,
+}
+
+/// An option to write with `write_device_options`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionWrite {
+    pub key: String,
+    pub value: String,
+}
+
+/// One written option: the value before the write and the value read back after.
+/// `applied` is set once the device has taken the new value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptionChange {
+    pub key: String,
+    pub previous: Option<String>,
+    pub value: Option<String>,
+    pub applied: bool,
+    pub error: Option<String>,      // Why the device refused the write
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionWriteResult {
+    pub device_key: String,
+    pub changes: Vec<OptionChange>,
+}
+
+impl ZKClient {
+    /// Get a device option value
+    pub(super) async fn get_option(&mut self, option: &str) -> Result<String, ZkError> {
+        Ok(self.read_option(option).await?.unwrap_or_default())
+    }
+    
+    /// Read one option; None when the device does not know the key
+    pub(super) async fn read_option(&mut self, option: &str) -> Result<Option<String>, ZkError> {
+        let mut cmd_data = option.as_bytes().to_vec();
+        cmd_data.push(0x00); // null terminate
+        
+        let (cmd, data) = self.send_command(CMD_OPTIONS_RRQ, &cmd_data).await?;
+        
+        if cmd == CMD_ACK_OK && !data.is_empty() {
+            // Response format: "option=value\0" - extract value after '='
+            let response = String::from_utf8_lossy(&data);
+            let response = response.trim_end_matches('\0');
+            
+            if let Some(pos) = response.find('=') {
+                let value = response[pos + 1..].to_string();
+                Ok(Some(value))
+            } else {
+                Ok(Some(response.to_string()))
+            }
+        } else if cmd == CMD_ACK_OK {
+            Ok(Some(String::new()))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    pub(super) async fn write_option(&mut self, option: &str, value: &str) -> Result<(), ZkError> {
+        let mut cmd_data = format!("{}={}", option, value).into_bytes();
+        cmd_data.push(0x00);
+        
+        let (cmd, _) = self.send_command(CMD_OPTIONS_WRQ, &cmd_data).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply(&format!("Failed to set option {}", option), cmd)) }
+    }
+    
+    pub(super) async fn refresh_options(&mut self) -> Result<(), ZkError> {
+        let (cmd, _) = self.send_command(CMD_REFRESHOPTION, &[]).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply("Failed to apply options", cmd)) }
+    }
+}
+
+/// Read the given options from a device
+pub async fn read_device_options(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    keys: Vec<String>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<DeviceOptionsReport, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let device_info = client.get_device_info().await;
+    let device_key = device_key(&device_info, &ip, port);
+    
+    let result: Result<Vec<DeviceOption>, ZkError> = async {
+        let mut options = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = client.read_option(&key).await?;
+            options.push(DeviceOption { key, value });
+        }
+        Ok(options)
+    }.await;
+    
+    client.disconnect().await?;
+    Ok(DeviceOptionsReport { device_info, device_key, options: result? })
+}
+
+/// Read every option in `KNOWN_OPTIONS`, for settings audits across a fleet
+pub async fn dump_device_options(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<DeviceOptionsReport, ZkError> {
+    let keys = KNOWN_OPTIONS.iter().map(|k| k.to_string()).collect();
+    read_device_options(ip, port, password, keys, settings).await
+}
+
+/// Write options, apply them with a refresh, and read each one back. An option
+/// the device refuses is reported in its change and the rest are still written;
+/// only a lost session stops the batch.
+pub async fn write_device_options(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    options: Vec<OptionWrite>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<OptionWriteResult, ZkError> {
+    if options.is_empty() {
+        return Err(ZkError::InvalidArgument("No options to write".to_string()));
+    }
+    for option in &options {
+        if option.key.is_empty() || option.key.contains(['=', '\0']) || option.value.contains('\0') {
+            return Err(ZkError::InvalidArgument(format!("Invalid option '{}'", option.key)));
+        }
+        if option.key.starts_with('~') {
+            return Err(ZkError::InvalidArgument(format!("Option {} is read-only", option.key)));
+        }
+    }
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    
+    let result: Result<OptionWriteResult, ZkError> = async {
+        client.load_settings(&ip, port, &settings).await;
+        let device_info = client.get_device_info().await;
+        let device_key = device_key(&device_info, &ip, port);
+        
+        let mut changes = Vec::with_capacity(options.len());
+        for option in &options {
+            let previous = client.read_option(&option.key).await?;
+            let error = match client.write_option(&option.key, &option.value).await {
+                Ok(()) => None,
+                Err(e @ (ZkError::UnexpectedCommand { .. } | ZkError::DeviceBusy(_))) => {
+                    warn!("⚠️ {} refused {}={:?}: {}", device_key, option.key, option.value, e);
+                    Some(e.to_string())
+                }
+                Err(e) => return Err(e),
+            };
+            changes.push(OptionChange { key: option.key.clone(), value: previous.clone(), previous, applied: false, error });
+        }
+        
+        let written = changes.iter().filter(|c| c.error.is_none()).count();
+        if written > 0 {
+            client.refresh_options().await?;
+        }
+        for (option, change) in options.iter().zip(changes.iter_mut()) {
+            if change.error.is_some() {
+                continue;
+            }
+            change.value = client.read_option(&option.key).await?;
+            change.applied = change.value.as_deref() == Some(option.value.as_str());
+            if !change.applied {
+                warn!("⚠️ {} reads back {:?} after writing {:?}", option.key, change.value, option.value);
+            }
+        }
+        info!("⚙️ Wrote {} of {} option(s) to {}", written, changes.len(), device_key);
+        Ok(OptionWriteResult { device_key, changes })
+    }.await;
+    
+    client.disconnect().await?;
+    result
+}
+
+#[cfg(test)]
+mod tests;
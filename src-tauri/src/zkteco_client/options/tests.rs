@@ -0,0 +1,51 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::collections::HashMap;
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn writes_options_and_reads_them_back() {
+    let mut options = HashMap::new();
+    options.insert("LockOn".to_string(), "10".to_string());
+    let sim = Simulator::start(SimConfig { options, ..device(72, 40) }).await;
+    
+    let report = dump_device_options("127.0.0.1", sim.port, None, settings("options")).await.unwrap();
+    let value = |key: &str| report.options.iter().find(|o| o.key == key).unwrap().value.clone();
+    assert_eq!(value("~DeviceName").as_deref(), Some("SIM-F18"));
+    assert_eq!(value("LockOn").as_deref(), Some("10"));
+    assert_eq!(value("VOLUME"), None);
+    
+    let writes = vec![OptionWrite { key: "LockOn".to_string(), value: "5".to_string() }];
+    let result = write_device_options("127.0.0.1", sim.port, None, writes, settings("options")).await.unwrap();
+    assert_eq!(result.changes, vec![OptionChange {
+        key: "LockOn".to_string(),
+        previous: Some("10".to_string()),
+        value: Some("5".to_string()),
+        applied: true,
+        error: None,
+    }]);
+    
+    let read_only = vec![OptionWrite { key: "~DeviceName".to_string(), value: "X".to_string() }];
+    assert!(write_device_options("127.0.0.1", sim.port, None, read_only, settings("options")).await.is_err());
+}
+
+#[tokio::test]
+async fn refused_option_does_not_stop_the_others() {
+    let mut options = HashMap::new();
+    options.insert("LockOn".to_string(), "10".to_string());
+    options.insert("VOLUME".to_string(), "60".to_string());
+    let locked_options = vec!["LockOn".to_string()];
+    let sim = Simulator::start(SimConfig { options, locked_options, ..device(72, 40) }).await;
+    
+    let writes = vec![
+        OptionWrite { key: "LockOn".to_string(), value: "5".to_string() },
+        OptionWrite { key: "VOLUME".to_string(), value: "80".to_string() },
+    ];
+    let result = write_device_options("127.0.0.1", sim.port, None, writes, settings("locked-options")).await.unwrap();
+    let lock = &result.changes[0];
+    assert!(!lock.applied && lock.error.is_some());
+    assert_eq!(lock.value.as_deref(), Some("10"));
+    let volume = &result.changes[1];
+    assert!(volume.applied && volume.error.is_none());
+    assert_eq!((volume.previous.as_deref(), volume.value.as_deref()), (Some("60"), Some("80")));
+}
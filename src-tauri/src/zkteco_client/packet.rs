@@ -0,0 +1,134 @@
+//! ZK packet framing: header, checksum, TCP wrapper and the auth commkey
+
+use super::*;
+
+/// Calculate checksum (matching pyzk __create_checksum exactly)
+fn calc_checksum(data: &[u8]) -> u16 {
+    let mut checksum: i32 = 0;
+    let mut i = 0;
+    
+    while i + 1 < data.len() {
+        let val = u16::from_le_bytes([data[i], data[i + 1]]) as i32;
+        checksum += val;
+        if checksum > USHRT_MAX as i32 {
+            checksum -= USHRT_MAX as i32;
+        }
+        i += 2;
+    }
+    
+    // Handle odd byte
+    if i < data.len() {
+        checksum += data[i] as i32;
+    }
+    
+    while checksum > USHRT_MAX as i32 {
+        checksum -= USHRT_MAX as i32;
+    }
+    
+    // Bitwise NOT (Python style)
+    checksum = !checksum;
+    
+    while checksum < 0 {
+        checksum += USHRT_MAX as i32;
+    }
+    
+    checksum as u16
+}
+
+/// Create packet header (matching pyzk __create_header)
+pub(super) fn create_packet(command: u16, session_id: u16, reply_id: u16, command_string: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&command.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&session_id.to_le_bytes());
+    buf.extend_from_slice(&reply_id.to_le_bytes());
+    buf.extend_from_slice(command_string);
+    
+    let checksum = calc_checksum(&buf);
+    
+    let mut next_reply_id = reply_id.wrapping_add(1);
+    if next_reply_id >= USHRT_MAX {
+        next_reply_id = next_reply_id.wrapping_sub(USHRT_MAX);
+    }
+    
+    let mut result = Vec::new();
+    result.extend_from_slice(&command.to_le_bytes());
+    result.extend_from_slice(&checksum.to_le_bytes());
+    result.extend_from_slice(&session_id.to_le_bytes());
+    result.extend_from_slice(&next_reply_id.to_le_bytes());
+    result.extend_from_slice(command_string);
+    
+    result
+}
+
+/// Create TCP top header
+pub(super) fn create_tcp_top(packet: &[u8]) -> Vec<u8> {
+    let length = packet.len() as u32;
+    let mut top = Vec::new();
+    top.extend_from_slice(&MACHINE_PREPARE_DATA_1.to_le_bytes());
+    top.extend_from_slice(&MACHINE_PREPARE_DATA_2.to_le_bytes());
+    top.extend_from_slice(&length.to_le_bytes());
+    top.extend_from_slice(packet);
+    top
+}
+
+impl ZKClient {
+    /// Create packet header for this session
+    pub(super) fn create_header(&self, command: u16, command_string: &[u8]) -> Vec<u8> {
+        create_packet(command, self.session_id, self.reply_id, command_string)
+    }
+    
+    /// Track session/reply ids from a received packet and split off its payload
+    pub(super) fn accept_packet(&mut self, data: &[u8]) -> (u16, Vec<u8>) {
+        let response_cmd = u16::from_le_bytes([data[0], data[1]]);
+        let response_session = u16::from_le_bytes([data[4], data[5]]);
+        let response_reply_id = u16::from_le_bytes([data[6], data[7]]);
+
+        if response_session != 0 {
+            self.session_id = response_session;
+        }
+        self.reply_id = response_reply_id;
+
+        let response_data = if data.len() > 8 { data[8..].to_vec() } else { Vec::new() };
+        (response_cmd, response_data)
+    }
+    
+    /// Make commkey for authentication
+    pub(super) fn make_commkey(password: u32, session_id: u16) -> Vec<u8> {
+        let key = password;
+        let session_id = session_id as u32;
+        
+        let mut k: u32 = 0;
+        for i in 0..32 {
+            if (key & (1 << i)) != 0 {
+                k = (k << 1) | 1;
+            } else {
+                k = k << 1;
+            }
+        }
+        k = k.wrapping_add(session_id);
+        
+        let k_bytes = k.to_le_bytes();
+        let xored = [
+            k_bytes[0] ^ b'Z',
+            k_bytes[1] ^ b'K',
+            k_bytes[2] ^ b'S',
+            k_bytes[3] ^ b'O',
+        ];
+        
+        let h1 = u16::from_le_bytes([xored[0], xored[1]]);
+        let h2 = u16::from_le_bytes([xored[2], xored[3]]);
+        let mut result = Vec::new();
+        result.extend_from_slice(&h2.to_le_bytes());
+        result.extend_from_slice(&h1.to_le_bytes());
+        
+        let b: u8 = 50;
+        let r = result.clone();
+        result[0] = r[0] ^ b;
+        result[1] = r[1] ^ b;
+        result[2] = b;
+        result[3] = r[3] ^ b;
+        
+        result
+    }
+}
@@ -0,0 +1,251 @@
+//! Attendance records and how each record layout is decoded
+
+use serde::{Deserialize, Serialize};
+use chrono_tz::Tz;
+use log::{info, warn};
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttendanceRecord {
+    pub user_id: u32,
+    pub user_name: String,
+    pub timestamp: String,  // ISO format for sorting
+    pub status: u8,         // Raw status from device (verify method)
+    pub punch: u8,          // Raw punch from device (punch state)
+    pub date: String,       // YYYY-MM-DD
+    pub time: String,       // HH:MM:SS
+    #[serde(default)]
+    pub verify: VerifyMethod,
+    #[serde(default)]
+    pub punch_state: PunchState,
+    #[serde(default)]
+    pub workcode: u32,      // 0 = none entered
+    #[serde(default)]
+    pub timestamp_invalid: bool,  // Device time was not a real date; timestamp holds "invalid:<raw hex>"
+}
+
+/// How the user identified themselves, decoded from the record's `status` byte
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyMethod {
+    Password,
+    Fingerprint,
+    Card,
+    Face,
+    Palm,
+    #[default]
+    Unknown,
+}
+
+impl VerifyMethod {
+    pub fn from_raw(status: u8) -> Self {
+        match status {
+            0 | 3 => VerifyMethod::Password,
+            1 => VerifyMethod::Fingerprint,
+            2 | 4 => VerifyMethod::Card,
+            15 => VerifyMethod::Face,
+            25 => VerifyMethod::Palm,
+            _ => VerifyMethod::Unknown,
+        }
+    }
+}
+
+/// The state key chosen on the terminal, decoded from the record's `punch` byte
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PunchState {
+    CheckIn,
+    CheckOut,
+    BreakOut,
+    BreakIn,
+    OvertimeIn,
+    OvertimeOut,
+    #[default]
+    Unknown,
+}
+
+impl PunchState {
+    pub fn from_raw(punch: u8) -> Self {
+        match punch {
+            0 => PunchState::CheckIn,
+            1 => PunchState::CheckOut,
+            2 => PunchState::BreakOut,
+            3 => PunchState::BreakIn,
+            4 => PunchState::OvertimeIn,
+            5 => PunchState::OvertimeOut,
+            _ => PunchState::Unknown,
+        }
+    }
+}
+
+impl ZKClient {
+    /// Parse raw attendance records (without the 4-byte size prefix)
+    pub(super) fn parse_attendance(attendance_data: &[u8], record_size: usize, users: &[User], zone: Option<Tz>) -> Vec<AttendanceRecord> {
+        let mut records = Vec::new();
+        let user_lookup = Self::build_user_lookup(users);
+        
+        // Parse based on record size
+        // pyzk handles: 8, 16, 40 byte records
+        info!("Attendance record size: {} bytes", record_size);
+        match record_size {
+            8 => {
+                // pyzk: uid, status, timestamp, punch = unpack('HB4sB', ...)
+                let mut offset = 0;
+                while offset + 8 <= attendance_data.len() {
+                    let record = &attendance_data[offset..offset + 8];
+                    
+                    let uid = u16::from_le_bytes([record[0], record[1]]);
+                    let status = record[2];
+                    let timestamp = u32::from_le_bytes([record[3], record[4], record[5], record[6]]);
+                    let punch = record[7];
+                    
+                    let user_id_str = uid.to_string();
+                    let user_name = user_lookup
+                        .get(&user_id_str)
+                        .cloned()
+                        .unwrap_or_else(|| format!("ID: {}", uid));
+                    
+                    let (timestamp, date, time, timestamp_invalid) =
+                        Self::record_time(Self::decode_time(timestamp, zone), &timestamp.to_le_bytes());
+                    
+                    records.push(AttendanceRecord {
+                        user_id: uid as u32,
+                        user_name,
+                        timestamp,
+                        status,
+                        punch,
+                        date,
+                        time,
+                        verify: VerifyMethod::from_raw(status),
+                        punch_state: PunchState::from_raw(punch),
+                        workcode: 0,
+                        timestamp_invalid,
+                    });
+                    
+                    offset += 8;
+                }
+            }
+            16 => {
+                // pyzk: user_id, timestamp, status, punch, reserved, workcode = 
+                //       unpack('<I4sBB2sI', ...)
+                let mut offset = 0;
+                let mut sample_logged = false;
+                while offset + 16 <= attendance_data.len() {
+                    let record = &attendance_data[offset..offset + 16];
+                    
+                    let user_id = u32::from_le_bytes([record[0], record[1], record[2], record[3]]);
+                    let timestamp = u32::from_le_bytes([record[4], record[5], record[6], record[7]]);
+                    let status = record[8];
+                    let punch = record[9];
+                    // reserved 2 bytes
+                    let workcode = u32::from_le_bytes([record[12], record[13], record[14], record[15]]);
+                    
+                    // Log first attendance record for debugging
+                    if !sample_logged {
+                        info!("Sample attendance: user_id={}, bytes={:02X?}", user_id, &record[0..4]);
+                        sample_logged = true;
+                    }
+                    
+                    let user_id_str = user_id.to_string();
+                    let user_name = user_lookup
+                        .get(&user_id_str)
+                        .cloned()
+                        .unwrap_or_else(|| format!("ID: {}", user_id));
+                    
+                    let (timestamp, date, time, timestamp_invalid) =
+                        Self::record_time(Self::decode_time(timestamp, zone), &timestamp.to_le_bytes());
+                    
+                    records.push(AttendanceRecord {
+                        user_id,
+                        user_name,
+                        timestamp,
+                        status,
+                        punch,
+                        date,
+                        time,
+                        verify: VerifyMethod::from_raw(status),
+                        punch_state: PunchState::from_raw(punch),
+                        workcode,
+                        timestamp_invalid,
+                    });
+                    
+                    offset += 16;
+                }
+            }
+            40 | _ => {
+                // pyzk 40-byte: uid, user_id, status, timestamp, punch, space =
+                //              unpack('<H24sB4sB8s', ...)
+                // where space starts with the workcode (I)
+                let actual_record_size = if record_size >= 40 { 40 } else { record_size };
+                let mut offset = 0;
+                let mut sample_logged = false;
+                
+                while offset + actual_record_size <= attendance_data.len() {
+                    let record = &attendance_data[offset..offset + actual_record_size];
+                    
+                    if actual_record_size >= 40 {
+                        let uid = u16::from_le_bytes([record[0], record[1]]);
+                        let user_id_bytes = &record[2..26];
+                        let status = record[26];
+                        let timestamp = u32::from_le_bytes([record[27], record[28], record[29], record[30]]);
+                        let punch = record[31];
+                        let workcode = u32::from_le_bytes([record[32], record[33], record[34], record[35]]);
+                        
+                        let user_id_str = String::from_utf8_lossy(user_id_bytes)
+                            .trim_end_matches('\0')
+                            .trim()
+                            .to_string();
+                        
+                        // Log first few attendance records for debugging
+                        if !sample_logged && records.len() < 3 {
+                            info!("  Attendance: uid={}, badge='{}', found={}", 
+                                uid, user_id_str, user_lookup.contains_key(&user_id_str));
+                            if records.len() >= 2 { sample_logged = true; }
+                        }
+                        
+                        let user_name = if !user_id_str.is_empty() {
+                            user_lookup.get(&user_id_str)
+                                .or_else(|| user_lookup.get(&uid.to_string()))
+                                .cloned()
+                                .unwrap_or_else(|| format!("ID: {}", user_id_str))
+                        } else {
+                            user_lookup.get(&uid.to_string())
+                            .cloned()
+                                .unwrap_or_else(|| format!("ID: {}", uid))
+                        };
+                        
+                        let (timestamp, date, time, timestamp_invalid) =
+                            Self::record_time(Self::decode_time(timestamp, zone), &timestamp.to_le_bytes());
+                        let final_user_id: u32 = user_id_str.parse().unwrap_or(uid as u32);
+                        
+                        records.push(AttendanceRecord {
+                            user_id: final_user_id,
+                            user_name,
+                            timestamp,
+                            status,
+                            punch,
+                            date,
+                            time,
+                            verify: VerifyMethod::from_raw(status),
+                            punch_state: PunchState::from_raw(punch),
+                            workcode,
+                            timestamp_invalid,
+                        });
+                    }
+                    
+                    offset += actual_record_size;
+                }
+            }
+        }
+        
+        info!("Parsed {} attendance records", records.len());
+        let invalid = records.iter().filter(|r| r.timestamp_invalid).count();
+        if invalid > 0 {
+            warn!("⚠️ {} attendance record(s) have an invalid device timestamp", invalid);
+        }
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests;
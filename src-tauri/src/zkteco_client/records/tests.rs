@@ -0,0 +1,46 @@
+use super::*;
+use chrono::NaiveDate;
+
+#[test]
+fn timestamps_follow_daylight_saving() {
+    let mut data = Vec::new();
+    for (month, day) in [(1, 15), (7, 15)] {
+        let time = NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        data.extend_from_slice(&1001u32.to_le_bytes());
+        data.extend_from_slice(&ZKClient::encode_time(&time).unwrap().to_le_bytes());
+        data.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+    
+    let records = ZKClient::parse_attendance(&data, 16, &[], Some(chrono_tz::America::New_York));
+    assert_eq!(records[0].timestamp, "2024-01-15T09:00:00-05:00");
+    assert_eq!(records[1].timestamp, "2024-07-15T09:00:00-04:00");
+    assert_eq!(records[1].time, "09:00:00");
+}
+
+#[test]
+fn times_skipped_by_spring_forward_take_the_summer_offset() {
+    // 02:30 never happened in New York on 2024-03-10; clocks jumped from 02:00 to 03:00
+    let gap = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap().and_hms_opt(2, 30, 0).unwrap();
+    let mut data = 1001u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&ZKClient::encode_time(&gap).unwrap().to_le_bytes());
+    data.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+    
+    let records = ZKClient::parse_attendance(&data, 16, &[], Some(chrono_tz::America::New_York));
+    assert!(!records[0].timestamp_invalid);
+    assert_eq!(records[0].timestamp, "2024-03-10T02:30:00-04:00");
+    assert_eq!(records[0].time, "02:30:00");
+}
+
+#[test]
+fn invalid_timestamps_are_flagged() {
+    // 2024-02-31 09:00:00 in device encoding
+    let raw: u32 = ((24 * 12 * 31 + 31 + 30) * 24 * 60 * 60) + 9 * 60 * 60;
+    let mut record = 1001u32.to_le_bytes().to_vec();
+    record.extend_from_slice(&raw.to_le_bytes());
+    record.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+    
+    let records = ZKClient::parse_attendance(&record, 16, &[], None);
+    assert!(records[0].timestamp_invalid);
+    assert_eq!(records[0].timestamp, format!("invalid:{}", to_hex(&raw.to_le_bytes())));
+    assert!(records[0].date.is_empty());
+}
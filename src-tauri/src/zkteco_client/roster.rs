@@ -0,0 +1,210 @@
+//! Roster sync: copying one device's users to others
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::{info, warn};
+use tokio::sync::Semaphore;
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Roster sync outcome for one target terminal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosterSyncTarget {
+    pub ip: String,
+    pub port: u16,
+    pub success: bool,
+    pub device_key: Option<String>,
+    pub users_added: Vec<String>,           // Badge IDs
+    pub users_updated: Vec<String>,
+    pub users_deleted: Vec<String>,         // Only with `delete_missing`
+    pub users_kept: Vec<String>,            // Only on the target, left in place
+    pub users_unchanged: usize,
+    pub templates: usize,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosterSyncResult {
+    pub source_key: String,
+    pub source_users: usize,
+    pub source_templates: usize,
+    pub dry_run: bool,                      // true = targets were only compared
+    pub targets: Vec<RosterSyncTarget>,
+}
+
+/// A user table with its templates, as read from a roster sync source
+struct Roster {
+    pub(super) fp_version: String,
+    pub(super) users: Vec<UserInput>,
+    pub(super) templates: Vec<FingerTemplate>,
+}
+
+/// Bring one target in line with the roster; a preview leaves the device enabled
+async fn sync_roster_target(
+    target: &DeviceTarget,
+    source_key: &str,
+    roster: &Roster,
+    delete_missing: bool,
+    dry_run: bool,
+    settings: &DeviceSettingsStore,
+) -> Result<RosterSyncTarget, ZkError> {
+    let mut client = ZKClient::connect(&target.ip, target.port, target.password.unwrap_or(0)).await?;
+    
+    let result: Result<RosterSyncTarget, ZkError> = async {
+        client.load_settings(&target.ip, target.port, settings).await;
+        let device_info = client.get_device_info().await;
+        let device_key = device_key(&device_info, &target.ip, target.port);
+        // The source under another address would lose every user added since the read
+        if device_key == source_key {
+            return Err(ZkError::InvalidArgument(format!("{} is the roster source", device_key)));
+        }
+        
+        if !dry_run {
+            client.disable_device().await?;
+        }
+        let upload = client.plan_user_upload(&roster.users, &roster.templates, &roster.fp_version).await?;
+        let (deleted, kept) = if delete_missing {
+            (upload.only_on_device.clone(), Vec::new())
+        } else {
+            (Vec::new(), upload.only_on_device.clone())
+        };
+        
+        if !dry_run {
+            if !upload.users.is_empty() {
+                client.save_user_templates(&upload.users, &upload.templates).await?;
+            }
+            for (uid, _) in &deleted {
+                client.delete_user(Some(*uid), None).await?;
+            }
+            info!("👥 Synced roster to {}: +{} ~{} -{}", device_key, upload.added.len(), upload.updated.len(), deleted.len());
+        }
+        
+        Ok(RosterSyncTarget {
+            ip: target.ip.clone(),
+            port: target.port,
+            success: true,
+            device_key: Some(device_key),
+            users_added: upload.added,
+            users_updated: upload.updated,
+            users_deleted: deleted.into_iter().map(|(_, id)| id).collect(),
+            users_kept: kept.into_iter().map(|(_, id)| id).collect(),
+            users_unchanged: upload.unchanged,
+            templates: upload.templates.len(),
+            warnings: upload.warnings,
+            error: None,
+        })
+    }.await;
+    
+    if dry_run {
+        client.close().await?;
+    } else {
+        client.disconnect().await?;
+    }
+    result
+}
+
+/// Make the user tables of `targets` match `source`: missing and changed users are
+/// pushed with their templates, and users only on a target are deleted when
+/// `delete_missing` is set. With `dry_run` the targets are only compared. Deleting
+/// is refused unless `confirm` is set; at most `max_concurrent` targets are written at a time.
+/// The source itself is never a target, whether listed by address or reached under another.
+pub async fn sync_user_roster(
+    source: DeviceTarget,
+    mut targets: Vec<DeviceTarget>,
+    delete_missing: bool,
+    dry_run: bool,
+    confirm: bool,
+    max_concurrent: usize,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<RosterSyncResult, ZkError> {
+    if delete_missing && !dry_run && !confirm {
+        return Err(ZkError::InvalidArgument("Deleting users missing from the source must be confirmed".to_string()));
+    }
+    targets.retain(|target| {
+        let is_source = target.ip == source.ip && target.port == source.port;
+        if is_source {
+            info!("👥 Skipping {}:{}, it is the roster source", target.ip, target.port);
+        }
+        !is_source
+    });
+    let mut client = ZKClient::connect(&source.ip, source.port, source.password.unwrap_or(0)).await?;
+    
+    let result: Result<(String, Roster), ZkError> = async {
+        client.load_settings(&source.ip, source.port, &settings).await;
+        let device_info = client.get_device_info().await;
+        let source_key = device_key(&device_info, &source.ip, source.port);
+        let fp_version = client.get_option("~ZKFPVersion").await.unwrap_or_default();
+        
+        client.disable_device().await?;
+        let users = client.get_users().await?;
+        let templates = client.get_templates().await?;
+        Ok((source_key, Roster {
+            fp_version,
+            users: users.iter().map(User::to_input).collect(),
+            templates,
+        }))
+    }.await;
+    
+    client.disconnect().await?;
+    let (source_key, roster) = result?;
+    let roster = Arc::new(roster);
+    info!("👥 Roster of {} ({} users) to {} target(s){}", source_key, roster.users.len(), targets.len(),
+        if dry_run { " (preview)" } else { "" });
+    
+    let semaphore = Arc::new(Semaphore::new(max_concurrent.max(1)));
+    let handles: Vec<_> = targets
+        .into_iter()
+        .map(|target| {
+            let semaphore = Arc::clone(&semaphore);
+            let roster = Arc::clone(&roster);
+            let settings = Arc::clone(&settings);
+            let source_key = source_key.clone();
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let result = sync_roster_target(&target, &source_key, &roster, delete_missing, dry_run, &settings).await;
+                (target, result)
+            })
+        })
+        .collect();
+    
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        let (target, result) = match handle.await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                warn!("Roster sync task failed: {}", e);
+                continue;
+            }
+        };
+        results.push(result.unwrap_or_else(|e| {
+            warn!("👥 Roster sync to {}:{} failed: {}", target.ip, target.port, e);
+            RosterSyncTarget {
+                ip: target.ip,
+                port: target.port,
+                success: false,
+                device_key: None,
+                users_added: Vec::new(),
+                users_updated: Vec::new(),
+                users_deleted: Vec::new(),
+                users_kept: Vec::new(),
+                users_unchanged: 0,
+                templates: 0,
+                warnings: Vec::new(),
+                error: Some(e.to_string()),
+            }
+        }));
+    }
+    
+    Ok(RosterSyncResult {
+        source_key,
+        source_users: roster.users.len(),
+        source_templates: roster.templates.len(),
+        dry_run,
+        targets: results,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::zk_simulator::{SimConfig, SimUser, Simulator};
+
+#[tokio::test]
+async fn roster_sync_previews_then_applies() {
+    let mut source = device(72, 40);
+    source.users[0].fingers = vec![1];
+    let source = Simulator::start(source).await;
+    
+    let mut renamed = SimUser::new(5, "1002", "Bala K");
+    renamed.fingers = vec![1];
+    let gate = Simulator::start(SimConfig {
+        users: vec![renamed, SimUser::new(9, "7777", "Leaver")],
+        serial_number: "SIM0000000003".to_string(),
+        ..device(72, 40)
+    }).await;
+    
+    let target = |sim: &Simulator| DeviceTarget { ip: "127.0.0.1".to_string(), port: sim.port, password: None };
+    let preview = sync_user_roster(target(&source), vec![target(&gate)], true, true, false, 1, settings("roster")).await.unwrap();
+    let plan = &preview.targets[0];
+    assert!(plan.success);
+    assert_eq!(plan.users_added, vec!["1001", "1003"]);
+    assert_eq!(plan.users_updated, vec!["1002"]);
+    assert_eq!(plan.users_deleted, vec!["7777"]);
+    assert_eq!(plan.templates, 1);
+    assert_eq!(gate.disables(), 0);
+    let untouched = list_device_users("127.0.0.1", gate.port, None, settings("roster")).await.unwrap();
+    assert_eq!(untouched.users.len(), 2);
+    
+    let err = sync_user_roster(target(&source), vec![target(&gate)], true, false, false, 1, settings("roster")).await.unwrap_err();
+    assert_eq!(err.code(), "invalid_argument");
+    assert_eq!(list_device_users("127.0.0.1", gate.port, None, settings("roster")).await.unwrap().users.len(), 2);
+    
+    sync_user_roster(target(&source), vec![target(&gate)], true, false, true, 1, settings("roster")).await.unwrap();
+    let users = list_device_users("127.0.0.1", gate.port, None, settings("roster")).await.unwrap().users;
+    let mut ids: Vec<_> = users.iter().map(|u| u.user_id.as_str()).collect();
+    ids.sort();
+    assert_eq!(ids, vec!["1001", "1002", "1003"]);
+    assert_eq!(users.iter().find(|u| u.user_id == "1002").unwrap().uid, 5);
+    
+    let again = sync_user_roster(target(&source), vec![target(&gate)], true, true, false, 1, settings("roster")).await.unwrap();
+    assert_eq!(again.targets[0].users_unchanged, 3);
+    assert!(again.targets[0].users_added.is_empty() && again.targets[0].users_updated.is_empty());
+}
+
+#[tokio::test]
+async fn roster_sync_never_writes_to_its_source() {
+    let source = Simulator::start(device(72, 40)).await;
+    // Same serial number as the source, reached on another port
+    let mirror = Simulator::start(SimConfig { users: vec![SimUser::new(9, "7777", "Leaver")], ..device(72, 40) }).await;
+    
+    let target = |sim: &Simulator| DeviceTarget { ip: "127.0.0.1".to_string(), port: sim.port, password: None };
+    let result = sync_user_roster(target(&source), vec![target(&source), target(&mirror)], true, false, true, 2, settings("roster-source"))
+        .await.unwrap();
+    assert_eq!(result.targets.len(), 1);
+    assert_eq!(result.targets[0].port, mirror.port);
+    assert!(!result.targets[0].success);
+    assert!(result.targets[0].error.as_deref().unwrap().contains("roster source"));
+    assert_eq!((source.disables(), mirror.disables()), (1, 0));
+    assert_eq!(list_device_users("127.0.0.1", mirror.port, None, settings("roster-source")).await.unwrap().users.len(), 1);
+}
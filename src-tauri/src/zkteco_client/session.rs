@@ -0,0 +1,230 @@
+//! Opening, driving and closing a device session
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use log::{info, warn};
+use tokio::net::{TcpStream, UdpSocket};
+use crate::device_settings::{DeviceSettings, DeviceSettingsStore};
+use crate::zk_error::ZkError;
+use crate::zk_trace::{TraceReplay, TraceWriter};
+use super::*;
+
+impl ZKClient {
+    pub(super) async fn connect(ip: &str, port: u16, password: u32) -> Result<Self, ZkError> {
+        Self::open(ip, port, password, Duration::from_secs(10), Duration::from_secs(30)).await
+    }
+    
+    /// Open a session over TCP, falling back to UDP for terminals that only speak UDP
+    pub(super) async fn open(
+        ip: &str,
+        port: u16,
+        password: u32,
+        connect_timeout: Duration,
+        io_timeout: Duration,
+    ) -> Result<Self, ZkError> {
+        info!("Connecting to {}:{}...", ip, port);
+        let addr: SocketAddr = format!("{}:{}", ip, port)
+            .parse()
+            .map_err(|e| ZkError::InvalidArgument(format!("Invalid address: {}", e)))?;
+        
+        match Self::open_tcp(addr, password, connect_timeout, io_timeout).await {
+            Ok(client) => Ok(client),
+            // A wrong password is a real answer from the device, UDP won't change it
+            Err(e @ ZkError::AuthFailed(_)) => Err(e),
+            Err(tcp_err) => {
+                warn!("TCP session to {} failed ({}), trying UDP", addr, tcp_err);
+                match Self::open_udp(addr, password, connect_timeout, io_timeout).await {
+                    Ok(client) => Ok(client),
+                    Err(e @ (ZkError::AuthFailed(_) | ZkError::DeviceBusy(_))) => Err(e),
+                    // Report the TCP failure kind; UDP silence is expected on TCP-only terminals
+                    Err(udp_err) => {
+                        let message = format!("Device unreachable at {}: TCP: {}; UDP: {}", addr, tcp_err, udp_err);
+                        Err(tcp_err.with_message(message))
+                    }
+                }
+            }
+        }
+    }
+    
+    async fn open_tcp(
+        addr: SocketAddr,
+        password: u32,
+        connect_timeout: Duration,
+        io_timeout: Duration,
+    ) -> Result<Self, ZkError> {
+        let stream = with_timeout(Some(connect_timeout), TcpStream::connect(addr)).await
+            .map_err(|e| ZkError::io("Failed to connect", e).during_connect())?;
+        
+        let mut client = ZKClient {
+            transport: Transport::new(Socket::Tcp(stream), TraceWriter::start("tcp", &addr.to_string())),
+            session_id: 0,
+            reply_id: USHRT_MAX - 1,
+            password,
+            user_packet_size: None,
+            settings: DeviceSettings::default(),
+            name_encoding: None,
+        };
+        
+        client.transport.set_read_timeout(Some(io_timeout))?;
+        client.transport.set_write_timeout(Some(io_timeout))?;
+        
+        client.do_handshake().await.map_err(ZkError::during_connect)?;
+        
+        Ok(client)
+    }
+    
+    async fn open_udp(
+        addr: SocketAddr,
+        password: u32,
+        connect_timeout: Duration,
+        io_timeout: Duration,
+    ) -> Result<Self, ZkError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await
+            .map_err(|e| ZkError::io("Failed to bind UDP socket", e))?;
+        socket.connect(addr).await
+            .map_err(|e| ZkError::io("Failed to connect", e))?;
+        
+        let mut client = ZKClient {
+            transport: Transport::new(Socket::Udp(socket), TraceWriter::start("udp", &addr.to_string())),
+            session_id: 0,
+            reply_id: USHRT_MAX - 1,
+            password,
+            user_packet_size: None,
+            settings: DeviceSettings::default(),
+            name_encoding: None,
+        };
+        
+        // No connection setup on UDP, so the handshake is what times out on a dead host
+        client.transport.set_read_timeout(Some(connect_timeout))?;
+        client.transport.set_write_timeout(Some(io_timeout))?;
+        
+        client.do_handshake().await.map_err(ZkError::during_connect)?;
+        
+        client.transport.set_read_timeout(Some(io_timeout))?;
+        
+        Ok(client)
+    }
+    
+    /// Session driven by a saved trace instead of a device
+    pub(super) async fn from_trace(path: &Path) -> Result<Self, ZkError> {
+        let replay = TraceReplay::load(path)?;
+        let mut client = ZKClient {
+            transport: Transport::new(Socket::Replay(replay), None),
+            session_id: 0,
+            reply_id: USHRT_MAX - 1,
+            password: 0,
+            user_packet_size: None,
+            settings: DeviceSettings::default(),
+            name_encoding: None,
+        };
+        client.do_handshake().await?;
+        Ok(client)
+    }
+    
+    /// Apply the stored settings for this device; returns its key (serial, or ip:port)
+    pub(super) async fn load_settings(&mut self, ip: &str, port: u16, store: &DeviceSettingsStore) -> String {
+        let serial = self.get_serial_number().await;
+        let key = if serial.is_empty() { format!("{}:{}", ip, port) } else { serial };
+        self.settings = store.get(&key);
+        key
+    }
+    
+    /// Send command and receive response
+    pub(super) async fn send_command(&mut self, command: u16, command_string: &[u8]) -> Result<(u16, Vec<u8>), ZkError> {
+        let buf = self.create_header(command, command_string);
+        self.transport.send(&buf).await?;
+        
+        let data = self.transport.recv().await?;
+        
+        if data.len() < 8 {
+            return Err(ZkError::ShortPacket(format!("Response too short: {} bytes", data.len())));
+        }
+        
+        Ok(self.accept_packet(&data))
+    }
+    
+    /// Receive one ZK packet (for draining follow-up packets)
+    pub(super) async fn recv_packet(&mut self) -> Result<(u16, Vec<u8>), ZkError> {
+        let data = self.transport.recv().await?;
+        if data.len() < 8 {
+            return Err(ZkError::ShortPacket(format!("Invalid packet length: {}", data.len())));
+        }
+        
+        Ok(self.accept_packet(&data))
+    }
+    
+    /// Handshake with device (with authentication support)
+    async fn do_handshake(&mut self) -> Result<(), ZkError> {
+        let (cmd, data) = self.send_command(CMD_CONNECT, &[]).await?;
+        
+        if cmd == CMD_ACK_UNAUTH {
+            let commkey = Self::make_commkey(self.password, self.session_id);
+            let (auth_cmd, _) = self.send_command(CMD_AUTH, &commkey).await?;
+            
+            if auth_cmd == CMD_ACK_OK {
+                info!("Connected over {} (authenticated)", self.transport.name());
+                Ok(())
+            } else if auth_cmd == CMD_ACK_UNAUTH {
+                Err(ZkError::AuthFailed("Authentication failed: wrong communication password".to_string()))
+            } else {
+                Err(ZkError::reply("Authentication failed", auth_cmd))
+            }
+        } else if cmd == CMD_ACK_OK {
+            if data.len() >= 2 {
+                self.session_id = u16::from_le_bytes([data[0], data[1]]);
+            }
+            info!("Connected over {}", self.transport.name());
+            Ok(())
+        } else {
+            Err(ZkError::reply("Handshake failed", cmd))
+        }
+    }
+    
+    pub(super) async fn disable_device(&mut self) -> Result<(), ZkError> {
+        let (cmd, _) = self.send_command(CMD_DISABLEDEVICE, &[]).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply("Failed to disable device", cmd)) }
+    }
+    
+    pub(super) async fn enable_device(&mut self) -> Result<(), ZkError> {
+        let (cmd, _) = self.send_command(CMD_ENABLEDEVICE, &[]).await?;
+        if cmd == CMD_ACK_OK { Ok(()) } else { Err(ZkError::reply("Failed to enable device", cmd)) }
+    }
+    
+    /// Large buffer read (captures multiple packets)
+    pub(super) async fn send_command_large_recv(&mut self, command: u16, command_string: &[u8]) -> Result<(u16, Vec<u8>), ZkError> {
+        let buf = self.create_header(command, command_string);
+        self.transport.send(&buf).await?;
+        
+        let old_timeout = self.transport.read_timeout();
+        let _ = self.transport.set_read_timeout(Some(std::time::Duration::from_secs(10)));
+        
+        let packet = self.transport.recv_burst().await;
+        let _ = self.transport.set_read_timeout(old_timeout);
+        let packet = packet?;
+        
+        let response_cmd = u16::from_le_bytes([packet[0], packet[1]]);
+        let response_session = u16::from_le_bytes([packet[4], packet[5]]);
+        let response_reply_id = u16::from_le_bytes([packet[6], packet[7]]);
+        
+        if response_session != 0 { self.session_id = response_session; }
+        self.reply_id = response_reply_id;
+        
+        Ok((response_cmd, packet))
+    }
+    
+    pub(super) async fn disconnect(&mut self) -> Result<(), ZkError> {
+        let _ = self.enable_device().await;
+        self.close().await
+    }
+    
+    /// End the session without re-enabling the device, for sessions that never disabled it
+    pub(super) async fn close(&mut self) -> Result<(), ZkError> {
+        let _ = self.send_command(CMD_EXIT, &[]).await;
+        info!("Disconnected");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
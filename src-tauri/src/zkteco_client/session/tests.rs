@@ -0,0 +1,54 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::net::SocketAddr;
+use std::time::Duration;
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn authenticates_with_comm_key() {
+    let sim = Simulator::start(SimConfig { password: 123456, ..device(72, 40) }).await;
+    let response = fetch(&sim, Some(123456)).await.unwrap();
+    assert_eq!(response.records.len(), 4);
+}
+
+#[tokio::test]
+async fn wrong_comm_key_is_auth_failure() {
+    let sim = Simulator::start(SimConfig { password: 123456, ..device(72, 40) }).await;
+    let err = fetch(&sim, Some(654321)).await.unwrap_err();
+    assert_eq!(err.code(), "auth_failed");
+    assert!(!err.retryable());
+}
+
+#[tokio::test]
+async fn closed_port_is_refused() {
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let err = connect_and_fetch_attendance("127.0.0.1", port, None, settings("refused")).await.unwrap_err();
+    assert_eq!(err.code(), "connection_refused");
+}
+
+#[tokio::test]
+async fn falls_back_to_udp_when_tcp_is_refused() {
+    // Nothing listens on TCP at the simulator's port, so the session must move to UDP
+    let sim = Simulator::start_udp(device(72, 40)).await;
+    let response = fetch(&sim, None).await.unwrap();
+    assert_eq!(response.records.len(), 4);
+    assert_eq!(response.device_info.serial_number, "SIM0000000001");
+    assert_eq!(response.records[0].user_name, "Alice");
+}
+
+#[tokio::test]
+async fn udp_session_authenticates_and_reads_users() {
+    let sim = Simulator::start_udp(SimConfig { password: 4321, ..device(28, 16) }).await;
+    let addr: SocketAddr = format!("127.0.0.1:{}", sim.port).parse().unwrap();
+    let mut client = ZKClient::open_udp(addr, 4321, Duration::from_secs(2), Duration::from_secs(2)).await.unwrap();
+    assert!(!client.transport.is_tcp());
+    let users = client.get_users().await.unwrap();
+    client.disconnect().await.unwrap();
+    assert_eq!(users.len(), 3);
+    
+    let wrong = ZKClient::open_udp(addr, 1, Duration::from_secs(2), Duration::from_secs(2)).await;
+    assert_eq!(wrong.err().map(|e| e.code()), Some("auth_failed"));
+}
@@ -0,0 +1,140 @@
+//! Template backups: users and finger templates saved to a file and restored
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use chrono::Local;
+use log::{info, warn};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Portable template backup: the user table plus every finger template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateBackup {
+    pub format_version: u32,
+    pub created_at: String,
+    pub device_info: DeviceInfo,
+    pub fp_version: String,       // Fingerprint algorithm (~ZKFPVersion), templates only work on the same one
+    #[serde(default)]
+    pub user_size: Option<usize>, // User record layout of the source: 28 or 72
+    pub users: Vec<UserInput>,
+    pub templates: Vec<FingerTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateTransferSummary {
+    pub path: String,
+    pub users: usize,             // Users written (saved, or added and changed on restore)
+    pub templates: usize,
+    #[serde(default)]
+    pub warnings: Vec<String>,    // Users the device could not hold
+}
+
+const TEMPLATE_BACKUP_VERSION: u32 = 1;
+
+/// Save every user and finger template of a device to a portable JSON file
+pub async fn backup_templates(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<TemplateTransferSummary, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let device_info = client.get_device_info().await;
+    let fp_version = client.get_option("~ZKFPVersion").await.unwrap_or_default();
+    
+    if let Err(e) = client.disable_device().await {
+        warn!("Failed to disable device: {}", e);
+    }
+    let users = client.get_users().await;
+    let templates = client.get_templates().await;
+    client.disconnect().await?;
+    
+    let backup = TemplateBackup {
+        format_version: TEMPLATE_BACKUP_VERSION,
+        created_at: Local::now().to_rfc3339(),
+        device_info,
+        fp_version,
+        user_size: client.user_packet_size,
+        users: users?.iter().map(User::to_input).collect(),
+        templates: templates?,
+    };
+    
+    let json = serde_json::to_string_pretty(&backup)
+        .map_err(|e| ZkError::Other(format!("Failed to serialize backup: {}", e)))?;
+    std::fs::write(&path, json)
+        .map_err(|e| ZkError::Other(format!("Failed to write {}: {}", path, e)))?;
+    
+    info!("💾 Saved {} users / {} templates to {}", backup.users.len(), backup.templates.len(), path);
+    Ok(TemplateTransferSummary {
+        path,
+        users: backup.users.len(),
+        templates: backup.templates.len(),
+        warnings: Vec::new(),
+    })
+}
+
+/// Upload the users and templates from a backup file to a device. Users are
+/// matched by badge like a roster sync, so people already on the device keep
+/// their slot and new ones never take the slot of someone else.
+pub async fn restore_templates(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    path: String,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<TemplateTransferSummary, ZkError> {
+    let ip = ip.to_string();
+    
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| ZkError::Other(format!("Failed to read {}: {}", path, e)))?;
+    let backup: TemplateBackup = serde_json::from_str(&content)
+        .map_err(|e| ZkError::Other(format!("Invalid template backup: {}", e)))?;
+    if backup.format_version > TEMPLATE_BACKUP_VERSION {
+        return Err(ZkError::Other(format!("Backup format v{} is newer than supported v{}",
+            backup.format_version, TEMPLATE_BACKUP_VERSION)));
+    }
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    // An empty device cannot show its user layout; the source's is the best guess
+    client.settings.user_size = client.settings.user_size.or(backup.user_size);
+    
+    let fp_version = client.get_option("~ZKFPVersion").await.unwrap_or_default();
+    if !fp_version.is_empty() && !backup.fp_version.is_empty() && fp_version != backup.fp_version {
+        let _ = client.disconnect().await;
+        return Err(ZkError::Other(format!(
+            "Fingerprint algorithm mismatch: backup is v{}, device is v{}",
+            backup.fp_version, fp_version
+        )));
+    }
+    
+    if let Err(e) = client.disable_device().await {
+        warn!("Failed to disable device: {}", e);
+    }
+    let result = async {
+        let upload = client.plan_user_upload(&backup.users, &backup.templates, &backup.fp_version).await?;
+        if !upload.users.is_empty() {
+            client.save_user_templates(&upload.users, &upload.templates).await?;
+        }
+        Ok::<_, ZkError>(upload)
+    }.await;
+    client.disconnect().await?;
+    let upload = result?;
+    
+    info!("📥 Restored {} users / {} templates from {} ({} unchanged)",
+        upload.users.len(), upload.templates.len(), path, upload.unchanged);
+    Ok(TemplateTransferSummary {
+        path,
+        users: upload.users.len(),
+        templates: upload.templates.len(),
+        warnings: upload.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,81 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::sync::Arc;
+use crate::zk_simulator::{SimConfig, SimUser, Simulator};
+
+#[tokio::test]
+async fn template_backup_restores_users_and_templates_onto_another_device() {
+    let mut config = device(72, 40);
+    config.users[0].fingers = vec![0, 6];
+    config.users[0].privilege = 14;
+    config.users[0].card = 5551234;
+    config.users[2].fingers = vec![1];
+    config.users[2].password = "42".to_string();
+    let source = Simulator::start(config).await;
+    let target = Simulator::start(SimConfig {
+        serial_number: "SIM0000000002".to_string(),
+        users: Vec::new(),
+        punches: Vec::new(),
+        ..device(72, 40)
+    }).await;
+    // The empty target cannot show its user layout; the backup carries the source's
+    let store = settings(&format!("templates-{}", source.port));
+    let file = |name: &str| std::env::temp_dir()
+        .join(format!("zk-templates-{}-{}-{}.json", std::process::id(), source.port, name))
+        .to_string_lossy().into_owned();
+    
+    let saved = backup_templates("127.0.0.1", source.port, None, file("source"), Arc::clone(&store)).await.unwrap();
+    assert_eq!((saved.users, saved.templates), (3, 3));
+    
+    // Real template bytes, not the simulator's stand-ins, must survive the trip
+    let mut backup: TemplateBackup = serde_json::from_str(&std::fs::read_to_string(file("source")).unwrap()).unwrap();
+    backup.templates[1].template = "4a4b0102fe".to_string();
+    std::fs::write(file("source"), serde_json::to_string(&backup).unwrap()).unwrap();
+    
+    let restored = restore_templates("127.0.0.1", target.port, None, file("source"), Arc::clone(&store)).await.unwrap();
+    assert_eq!((restored.users, restored.templates), (3, 3));
+    
+    backup_templates("127.0.0.1", target.port, None, file("target"), Arc::clone(&store)).await.unwrap();
+    let copy: TemplateBackup = serde_json::from_str(&std::fs::read_to_string(file("target")).unwrap()).unwrap();
+    assert_eq!(serde_json::to_value(&copy.users).unwrap(), serde_json::to_value(&backup.users).unwrap());
+    let key = |t: &FingerTemplate| (t.uid, t.fid, t.valid, t.template.clone());
+    let mut expected: Vec<_> = backup.templates.iter().map(key).collect();
+    let mut actual: Vec<_> = copy.templates.iter().map(key).collect();
+    expected.sort();
+    actual.sort();
+    assert_eq!(actual, expected);
+    
+    for name in ["source", "target"] {
+        let _ = std::fs::remove_file(file(name));
+    }
+}
+
+#[tokio::test]
+async fn template_restore_matches_users_by_badge() {
+    let mut config = device(72, 40);
+    config.users[0].fingers = vec![0];
+    let source = Simulator::start(config).await;
+    let mut other = SimUser::new(1, "9000", "Other");
+    other.fingers = vec![3];
+    let target = Simulator::start(SimConfig {
+        serial_number: "SIM0000000002".to_string(),
+        users: vec![other, SimUser::new(7, "1002", "Bala")],
+        ..device(72, 40)
+    }).await;
+    let store = settings(&format!("template-match-{}", source.port));
+    let path = std::env::temp_dir()
+        .join(format!("zk-templates-{}-{}-match.json", std::process::id(), source.port))
+        .to_string_lossy().into_owned();
+    
+    backup_templates("127.0.0.1", source.port, None, path.clone(), Arc::clone(&store)).await.unwrap();
+    let restored = restore_templates("127.0.0.1", target.port, None, path.clone(), Arc::clone(&store)).await.unwrap();
+    assert_eq!((restored.users, restored.templates), (2, 1));
+    
+    let users = list_device_users("127.0.0.1", target.port, None, Arc::clone(&store)).await.unwrap().users;
+    let find = |id: &str| users.iter().find(|u| u.user_id == id).unwrap().clone();
+    assert_eq!((find("9000").uid, find("9000").finger_count), (1, 1));
+    assert_eq!(find("1002").uid, 7);
+    assert_eq!(find("1001").finger_count, 1);
+    assert!(![1, 7].contains(&find("1001").uid) && ![1, 7].contains(&find("1003").uid));
+    let _ = std::fs::remove_file(&path);
+}
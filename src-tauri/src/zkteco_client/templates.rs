@@ -0,0 +1,109 @@
+//! Finger template download and the combined user/template upload
+
+use serde::{Deserialize, Serialize};
+use log::{info, warn};
+use encoding_rs::UTF_8;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// One enrolled finger template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FingerTemplate {
+    pub uid: u16,
+    pub fid: u8,         // Finger index 0-9
+    pub valid: u8,       // 1 = valid, 3 = duress finger
+    pub template: String, // Template bytes, hex encoded
+}
+
+impl ZKClient {
+    /// Download all finger templates (pyzk get_templates)
+    pub(super) async fn get_templates(&mut self) -> Result<Vec<FingerTemplate>, ZkError> {
+        let (data, _) = self.read_with_buffer_pyzk(CMD_DB_RRQ, FCT_FINGERTMP).await?;
+        let mut templates = Vec::new();
+        
+        if data.len() < 4 {
+            return Ok(templates);
+        }
+        
+        let mut total_size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let mut rest = &data[4..];
+        
+        // size(H), uid(H), fid(b), valid(b), template(size - 6)
+        while total_size > 0 && rest.len() >= 6 {
+            let size = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            if size < 6 || size > rest.len() {
+                warn!("Malformed template entry (size {}), stopping", size);
+                break;
+            }
+            templates.push(FingerTemplate {
+                uid: u16::from_le_bytes([rest[2], rest[3]]),
+                fid: rest[4],
+                valid: rest[5],
+                template: to_hex(&rest[6..size]),
+            });
+            rest = &rest[size..];
+            total_size = total_size.saturating_sub(size);
+        }
+        
+        info!("Found {} finger templates", templates.len());
+        Ok(templates)
+    }
+    
+    /// Write users and their templates in one batch (pyzk HR_save_usertemplates)
+    pub(super) async fn save_user_templates(&mut self, users: &[UserInput], templates: &[FingerTemplate]) -> Result<(), ZkError> {
+        if self.user_packet_size.is_none() {
+            self.get_users().await?;
+        }
+        let record_size = self.user_packet_size.unwrap_or(28);
+        
+        let mut upack = Vec::new();
+        let mut table = Vec::new();
+        let mut fpack = Vec::new();
+        
+        for user in users {
+            let uid = user.uid.ok_or_else(|| ZkError::InvalidArgument(format!("User {} has no uid", user.user_id)))?;
+            
+            // repack29 / repack73: leading 2, then the normal record
+            upack.push(2u8);
+            let mut record = Self::encode_user(record_size, uid, user, self.name_encoding.unwrap_or(UTF_8))?;
+            if record_size == 72 {
+                record[39] = 1;
+            }
+            upack.extend(record);
+            
+            for finger in templates.iter().filter(|t| t.uid == uid) {
+                let template = from_hex(&finger.template)
+                    .map_err(|e| ZkError::InvalidArgument(format!("Template of uid {} finger {}: {}", uid, finger.fid, e)))?;
+                // table entry: pack('<bHbI', 2, uid, 0x10 + fid, offset into fpack)
+                table.push(2u8);
+                table.extend_from_slice(&uid.to_le_bytes());
+                table.push(0x10 + finger.fid);
+                table.extend_from_slice(&(fpack.len() as u32).to_le_bytes());
+                
+                fpack.extend_from_slice(&(template.len() as u16).to_le_bytes());
+                fpack.extend(template);
+            }
+        }
+        
+        let mut packet = Vec::with_capacity(12 + upack.len() + table.len() + fpack.len());
+        packet.extend_from_slice(&(upack.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&(fpack.len() as u32).to_le_bytes());
+        packet.extend(upack);
+        packet.extend(table);
+        packet.extend(fpack);
+        
+        self.send_with_buffer(&packet).await?;
+        
+        let mut cmd_string = Vec::with_capacity(8);
+        cmd_string.extend_from_slice(&12u32.to_le_bytes());
+        cmd_string.extend_from_slice(&0u16.to_le_bytes());
+        cmd_string.extend_from_slice(&8u16.to_le_bytes());
+        let (cmd, _) = self.send_command(CMD_SAVE_USERTEMPS, &cmd_string).await?;
+        if cmd != CMD_ACK_OK {
+            return Err(ZkError::reply("Device failed to save users/templates", cmd));
+        }
+        
+        self.refresh_data().await
+    }
+}
@@ -0,0 +1,61 @@
+//! Simulated devices and stores shared by the client tests
+
+use super::*;
+use std::sync::Arc;
+use chrono::{NaiveDateTime, NaiveDate};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use crate::zk_simulator::{SimConfig, SimPunch, SimUser, Simulator};
+
+pub(super) fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+}
+
+pub(super) fn device(user_size: usize, record_size: usize) -> SimConfig {
+    let users = vec![
+        SimUser::new(1, "1001", "Alice"),
+        SimUser::new(2, "1002", "Bala"),
+        SimUser::new(3, "1003", "Chitra"),
+    ];
+    let punches = vec![
+        SimPunch::new(&users[0], at(15, 9, 0), 0),
+        SimPunch::new(&users[1], at(15, 9, 5), 0),
+        SimPunch { workcode: 7, ..SimPunch::new(&users[0], at(15, 18, 2), 1) },
+        SimPunch::new(&users[2], at(16, 8, 55), 0),
+    ];
+    SimConfig {
+        user_size,
+        record_size,
+        users,
+        punches,
+        ..SimConfig::default()
+    }
+}
+
+/// Empty settings store private to one test
+pub(super) fn settings(name: &str) -> Arc<DeviceSettingsStore> {
+    let path = std::env::temp_dir().join(format!("zk-settings-{}-{}.json", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    Arc::new(DeviceSettingsStore::load(path))
+}
+
+pub(super) async fn fetch(sim: &Simulator, password: Option<u32>) -> Result<AttendanceResponse, ZkError> {
+    connect_and_fetch_attendance("127.0.0.1", sim.port, password, settings(&sim.port.to_string())).await
+}
+
+pub(super) fn user_input(uid: Option<u16>, user_id: &str, name: &str) -> UserInput {
+    UserInput {
+        uid,
+        user_id: user_id.to_string(),
+        name: name.to_string(),
+        privilege: 0,
+        password: String::new(),
+        card: 0,
+        group_id: None,
+    }
+}
+
+pub(super) async fn user_ids(sim: &Simulator, store: &Arc<DeviceSettingsStore>) -> Vec<(u16, String, String)> {
+    list_device_users("127.0.0.1", sim.port, None, Arc::clone(store)).await.unwrap()
+        .users.into_iter().map(|u| (u.uid, u.user_id, u.name)).collect()
+}
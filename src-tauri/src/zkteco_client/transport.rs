@@ -0,0 +1,232 @@
+//! Socket carrying ZK packets over TCP, UDP or a replayed trace
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use crate::zk_error::ZkError;
+use crate::zk_trace::{TraceReplay, TraceWriter};
+use super::*;
+
+pub(super) enum Socket {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+    Replay(TraceReplay),
+}
+
+/// Socket carrying ZK packets (TCP-wrapped frames or bare UDP datagrams).
+/// tokio sockets have no timeouts of their own, so each operation is time-boxed here.
+pub(super) struct Transport {
+    pub(super) socket: Socket,
+    pub(super) read_timeout: Option<Duration>,
+    pub(super) write_timeout: Option<Duration>,
+    pub(super) trace: Option<TraceWriter>,
+}
+
+/// Run a socket operation under an optional timeout
+pub(super) async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    fut: impl Future<Output = std::io::Result<T>>,
+) -> std::io::Result<T> {
+    match timeout {
+        Some(t) => tokio::time::timeout(t, fut)
+            .await
+            .unwrap_or_else(|_| Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out"))),
+        None => fut.await,
+    }
+}
+
+impl Transport {
+    pub(super) fn new(socket: Socket, trace: Option<TraceWriter>) -> Self {
+        Transport {
+            socket,
+            read_timeout: None,
+            write_timeout: None,
+            trace,
+        }
+    }
+    
+    pub(super) fn name(&self) -> &'static str {
+        match &self.socket {
+            Socket::Tcp(_) => "TCP",
+            Socket::Udp(_) => "UDP",
+            Socket::Replay(replay) if replay.is_tcp() => "TCP (replay)",
+            Socket::Replay(_) => "UDP (replay)",
+        }
+    }
+    
+    pub(super) fn is_tcp(&self) -> bool {
+        match &self.socket {
+            Socket::Tcp(_) => true,
+            Socket::Udp(_) => false,
+            Socket::Replay(replay) => replay.is_tcp(),
+        }
+    }
+    
+    pub(super) fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+    
+    pub(super) fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<(), ZkError> {
+        self.read_timeout = timeout;
+        Ok(())
+    }
+    
+    pub(super) fn set_write_timeout(&mut self, timeout: Option<Duration>) -> Result<(), ZkError> {
+        self.write_timeout = timeout;
+        Ok(())
+    }
+    
+    pub(super) fn record(&mut self, dir: &str, kind: &str, data: &[u8]) {
+        if let Some(trace) = &mut self.trace {
+            trace.record(dir, kind, data);
+        }
+    }
+    
+    /// Trace the outcome of a read, including timeouts and other failures
+    fn record_read(&mut self, kind: &str, result: Result<&[u8], &ZkError>) {
+        match result {
+            Ok(data) => self.record("rx", kind, data),
+            Err(e) => if let Some(trace) = &mut self.trace {
+                trace.record_failure(e);
+            },
+        }
+    }
+    
+    /// Send one ZK packet (adds the TCP top header when on TCP)
+    pub(super) async fn send(&mut self, packet: &[u8]) -> Result<(), ZkError> {
+        self.record("tx", "packet", packet);
+        let timeout = self.write_timeout;
+        match &mut self.socket {
+            Socket::Tcp(stream) => {
+                let top = create_tcp_top(packet);
+                with_timeout(timeout, stream.write_all(&top)).await
+                    .map_err(|e| ZkError::io("Failed to send", e))?;
+                with_timeout(timeout, stream.flush()).await
+                    .map_err(|e| ZkError::io("Failed to flush", e))
+            }
+            Socket::Udp(socket) => {
+                with_timeout(timeout, socket.send(packet)).await
+                    .map(|_| ())
+                    .map_err(|e| ZkError::io("Failed to send", e))
+            }
+            Socket::Replay(replay) => replay.expect_send(packet),
+        }
+    }
+    
+    /// Receive one ZK packet (header + payload, TCP top header stripped)
+    pub(super) async fn recv(&mut self) -> Result<Vec<u8>, ZkError> {
+        let result = self.recv_packet().await;
+        self.record_read("packet", result.as_deref());
+        result
+    }
+    
+    pub(super) async fn recv_packet(&mut self) -> Result<Vec<u8>, ZkError> {
+        let timeout = self.read_timeout;
+        let data = match &mut self.socket {
+            Socket::Tcp(stream) => {
+                let mut tcp_header = [0u8; 8];
+                with_timeout(timeout, stream.read_exact(&mut tcp_header)).await
+                    .map_err(|e| ZkError::io("Failed to read TCP header", e))?;
+                
+                let h1 = u16::from_le_bytes([tcp_header[0], tcp_header[1]]);
+                let h2 = u16::from_le_bytes([tcp_header[2], tcp_header[3]]);
+                if h1 != MACHINE_PREPARE_DATA_1 || h2 != MACHINE_PREPARE_DATA_2 {
+                    return Err(ZkError::BadMagic(format!("Invalid TCP header: {:02X?}", tcp_header)));
+                }
+                
+                let tcp_length = u32::from_le_bytes([tcp_header[4], tcp_header[5], tcp_header[6], tcp_header[7]]) as usize;
+                
+                let mut data = vec![0u8; tcp_length];
+                with_timeout(timeout, stream.read_exact(&mut data)).await
+                    .map_err(|e| ZkError::io("Failed to read packet data", e))?;
+                data
+            }
+            Socket::Udp(socket) => {
+                let mut buf = vec![0u8; UDP_RECV_BUF];
+                let n = with_timeout(timeout, socket.recv(&mut buf)).await
+                    .map_err(|e| ZkError::io("Failed to receive datagram", e))?;
+                buf.truncate(n);
+                buf
+            }
+            Socket::Replay(replay) => replay.next_rx("packet")?,
+        };
+        Ok(data)
+    }
+    
+    /// Single read of whatever the device sent first. On TCP this may hold
+    /// more than one frame; the returned bytes start at the first ZK header.
+    pub(super) async fn recv_burst(&mut self) -> Result<Vec<u8>, ZkError> {
+        if !self.is_tcp() {
+            let data = self.recv().await?;
+            if data.len() < 8 {
+                return Err(ZkError::ShortPacket(format!("Response too short: {} bytes", data.len())));
+            }
+            return Ok(data);
+        }
+        
+        let result = self.read_burst().await;
+        self.record_read("burst", result.as_deref());
+        result
+    }
+    
+    async fn read_burst(&mut self) -> Result<Vec<u8>, ZkError> {
+        let timeout = self.read_timeout;
+        let data = match &mut self.socket {
+            Socket::Tcp(stream) => {
+                let mut large_buf = vec![0u8; 1032];
+                let bytes_read = with_timeout(timeout, stream.read(&mut large_buf)).await
+                    .map_err(|e| ZkError::io("Read failed", e))?;
+                
+                if bytes_read < 16 {
+                    return Err(ZkError::ShortPacket(format!("Response too short: {} bytes", bytes_read)));
+                }
+                
+                let tcp_magic1 = u16::from_le_bytes([large_buf[0], large_buf[1]]);
+                let tcp_magic2 = u16::from_le_bytes([large_buf[2], large_buf[3]]);
+                
+                if tcp_magic1 != MACHINE_PREPARE_DATA_1 || tcp_magic2 != MACHINE_PREPARE_DATA_2 {
+                    return Err(ZkError::BadMagic("Invalid TCP magic".to_string()));
+                }
+                
+                large_buf[8..bytes_read].to_vec()
+            }
+            Socket::Replay(replay) => replay.next_rx("burst")?,
+            Socket::Udp(_) => unreachable!("UDP handled above"),
+        };
+        Ok(data)
+    }
+    
+    /// Read raw stream bytes that belong to the current TCP frame
+    pub(super) async fn read_exact_raw(&mut self, buf: &mut [u8]) -> Result<(), ZkError> {
+        let result = self.fill_raw(buf).await;
+        self.record_read("raw", result.as_ref().map(|_| &*buf));
+        result
+    }
+    
+    async fn fill_raw(&mut self, buf: &mut [u8]) -> Result<(), ZkError> {
+        let timeout = self.read_timeout;
+        match &mut self.socket {
+            Socket::Tcp(stream) => {
+                with_timeout(timeout, stream.read_exact(buf)).await
+                    .map_err(|e| ZkError::io("Read remaining", e))?;
+            }
+            Socket::Replay(replay) if replay.is_tcp() => {
+                let data = replay.next_rx("raw")?;
+                if data.len() != buf.len() {
+                    return Err(ZkError::Protocol(format!(
+                        "Replay diverged: reading {} raw bytes but trace has {}", buf.len(), data.len()
+                    )));
+                }
+                buf.copy_from_slice(&data);
+            }
+            Socket::Udp(_) | Socket::Replay(_) => {
+                return Err(ZkError::Protocol("Raw reads are not supported over UDP".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
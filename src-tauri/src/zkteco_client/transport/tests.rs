@@ -0,0 +1,102 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::zk_trace::{TraceReplay, TraceWriter};
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn replays_a_recorded_simulator_session() {
+    let sim = Simulator::start(SimConfig { password: 123456, ..device(72, 40) }).await;
+    let dir = std::env::temp_dir().join(format!("zk-traces-{}-{}", std::process::id(), sim.port));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store = settings(&format!("trace-{}", sim.port));
+    
+    // Tracing is process-wide; other tests' sessions may land in the directory too
+    crate::zk_trace::set_trace_dir(Some(dir.clone())).unwrap();
+    let recorded = connect_and_fetch_attendance("127.0.0.1", sim.port, Some(123456), Arc::clone(&store)).await;
+    crate::zk_trace::set_trace_dir(None).unwrap();
+    let recorded = recorded.unwrap();
+    
+    let prefix = format!("127-0-0-1-{}_tcp_", sim.port);
+    let path = std::fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.file_name().unwrap().to_string_lossy().starts_with(&prefix))
+        .unwrap();
+    let trace = std::fs::read_to_string(&path).unwrap();
+    let auth: crate::zk_trace::TraceEntry = trace.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .find(|entry: &crate::zk_trace::TraceEntry| entry.cmd == Some(CMD_AUTH))
+        .unwrap();
+    assert!(auth.hex.ends_with("00000000"), "commkey left in trace: {}", auth.hex);
+    
+    let replayed = replay_attendance_trace(&path.to_string_lossy(), store).await.unwrap();
+    assert_eq!(replayed.device_info.serial_number, recorded.device_info.serial_number);
+    assert_eq!(
+        serde_json::to_value(&replayed.records).unwrap(),
+        serde_json::to_value(&recorded.records).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn trace_replays_read_timeouts_in_place() {
+    let device = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.connect(device.local_addr().unwrap()).await.unwrap();
+    let path = std::env::temp_dir().join(format!("zk-timeout-{}-{}.zktrace.jsonl", std::process::id(), socket.local_addr().unwrap().port()));
+    
+    let request = create_packet(CMD_GET_TIME, 7, 1, &[]);
+    let reply = create_packet(CMD_ACK_OK, 7, 1, &[1, 2, 3, 4]);
+    {
+        let writer = TraceWriter::create(path.clone(), "udp", "127.0.0.1:4370");
+        let mut transport = Transport::new(Socket::Udp(socket), writer);
+        transport.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        transport.send(&request).await.unwrap();
+        assert!(transport.recv().await.unwrap_err().is_timeout());
+        let (_, peer) = device.recv_from(&mut [0u8; 64]).await.unwrap();
+        device.send_to(&reply, peer).await.unwrap();
+        assert_eq!(transport.recv().await.unwrap(), reply);
+    }
+    
+    let mut replay = Transport::new(Socket::Replay(TraceReplay::load(&path).unwrap()), None);
+    // A read in the wrong direction is refused without using up the entry
+    assert_eq!(replay.recv().await.unwrap_err().code(), "protocol");
+    replay.send(&request).await.unwrap();
+    assert_eq!(replay.recv().await.unwrap_err().code(), "timeout");
+    assert_eq!(replay.recv().await.unwrap(), reply);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn user_writes_are_traced_without_pin_or_card() {
+    let path = std::env::temp_dir().join(format!("zk-user-wrq-{}.zktrace.jsonl", std::process::id()));
+    let mut short = vec![0u8; 28];
+    short[3..7].copy_from_slice(b"4321");
+    short[8..11].copy_from_slice(b"Ann");
+    short[16..20].copy_from_slice(&0xDEADBEEFu32.to_le_bytes());
+    let mut long = vec![0u8; 72];
+    long[3..11].copy_from_slice(b"87654321");
+    long[11..14].copy_from_slice(b"Bob");
+    long[35..39].copy_from_slice(&0xCAFEF00Du32.to_le_bytes());
+    {
+        let mut writer = TraceWriter::create(path.clone(), "tcp", "127.0.0.1:4370").unwrap();
+        writer.record("tx", "packet", &create_packet(CMD_USER_WRQ, 1, 2, &short));
+        writer.record("tx", "packet", &create_packet(CMD_USER_WRQ, 1, 3, &long));
+    }
+    
+    let trace = std::fs::read_to_string(&path).unwrap();
+    let writes: Vec<Vec<u8>> = trace.lines()
+        .filter_map(|line| serde_json::from_str::<crate::zk_trace::TraceEntry>(line).ok())
+        .filter(|entry| entry.cmd == Some(CMD_USER_WRQ))
+        .map(|entry| from_hex(&entry.hex).unwrap()[8..].to_vec())
+        .collect();
+    assert_eq!(writes.len(), 2);
+    assert_eq!(&writes[0][3..8], &[0; 5]);
+    assert_eq!(&writes[0][8..11], b"Ann");
+    assert_eq!(&writes[0][16..20], &[0; 4]);
+    assert_eq!(&writes[1][3..11], &[0; 8]);
+    assert_eq!(&writes[1][11..14], b"Bob");
+    assert_eq!(&writes[1][35..39], &[0; 4]);
+    let _ = std::fs::remove_file(&path);
+}
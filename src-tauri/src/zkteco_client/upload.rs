@@ -0,0 +1,110 @@
+//! Matching users and templates from another device against this one
+
+use std::collections::HashSet;
+use encoding_rs::UTF_8;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Users copied from another device, compared with the ones on this device
+#[derive(Debug, Default)]
+pub(super) struct UserUpload {
+    pub(super) users: Vec<UserInput>,              // New and changed users, with this device's uids
+    pub(super) templates: Vec<FingerTemplate>,     // Their finger templates, with this device's uids
+    pub(super) added: Vec<String>,                 // Badge IDs
+    pub(super) updated: Vec<String>,
+    pub(super) unchanged: usize,
+    pub(super) only_on_device: Vec<(u16, String)>, // (uid, badge ID)
+    pub(super) warnings: Vec<String>,
+}
+
+impl ZKClient {
+    /// Match users and templates from another device to this one by badge ID.
+    /// Matched users keep this device's uid; new users keep theirs unless it is
+    /// taken here. A user counts as changed when a field differs or one of its
+    /// templates is missing here. Users this layout cannot hold and templates of
+    /// another fingerprint algorithm are skipped with a warning.
+    pub(super) async fn plan_user_upload(
+        &mut self,
+        users: &[UserInput],
+        templates: &[FingerTemplate],
+        fp_version: &str,
+    ) -> Result<UserUpload, ZkError> {
+        let on_device = self.get_users().await?;
+        let record_size = self.user_packet_size.unwrap_or(28);
+        let encoding = self.name_encoding.unwrap_or(UTF_8);
+        let mut upload = UserUpload::default();
+        
+        let device_fp = self.get_option("~ZKFPVersion").await.unwrap_or_default();
+        let templates = if device_fp.is_empty() || fp_version.is_empty() || device_fp == fp_version {
+            templates
+        } else {
+            upload.warnings.push(format!(
+                "{} finger templates skipped: source is fingerprint v{}, device is v{}",
+                templates.len(), fp_version, device_fp
+            ));
+            &[]
+        };
+        let device_templates = if templates.is_empty() { Vec::new() } else { self.get_templates().await? };
+        
+        let mut taken: HashSet<u16> = on_device.iter().map(|u| u.uid as u16).collect();
+        let mut next_free = taken.iter()
+            .chain(users.iter().filter_map(|u| u.uid.as_ref()))
+            .max()
+            .copied()
+            .unwrap_or(0)
+            .saturating_add(1);
+        
+        for user in users {
+            let existing = on_device.iter().find(|u| u.user_id == user.user_id);
+            let uid = match (existing, user.uid.filter(|uid| !taken.contains(uid))) {
+                (Some(existing), _) => existing.uid as u16,
+                (None, Some(uid)) => uid,
+                (None, None) => {
+                    next_free = next_free.saturating_add(1);
+                    next_free - 1
+                }
+            };
+            taken.insert(uid);
+            
+            let candidate = UserInput { uid: Some(uid), ..user.clone() };
+            if let Err(e) = Self::encode_user(record_size, uid, &candidate, encoding) {
+                upload.warnings.push(format!("User {} skipped: {}", user.user_id, e));
+                continue;
+            }
+            
+            let fingers: Vec<FingerTemplate> = templates.iter()
+                .filter(|t| user.uid == Some(t.uid))
+                .map(|t| FingerTemplate { uid, ..t.clone() })
+                .collect();
+            match existing {
+                None => upload.added.push(user.user_id.clone()),
+                Some(existing) => {
+                    let fingers_present = fingers.iter().all(|f| {
+                        device_templates.iter().any(|t| t.uid == uid && t.fid == f.fid && t.template == f.template)
+                    });
+                    if same_user(user, existing) && fingers_present {
+                        upload.unchanged += 1;
+                        continue;
+                    }
+                    upload.updated.push(user.user_id.clone());
+                }
+            }
+            upload.templates.extend(fingers);
+            upload.users.push(candidate);
+        }
+        
+        upload.only_on_device = on_device.iter()
+            .filter(|u| !users.iter().any(|a| a.user_id == u.user_id))
+            .map(|u| (u.uid as u16, u.user_id.clone()))
+            .collect();
+        Ok(upload)
+    }
+}
+
+fn same_user(archived: &UserInput, on_device: &User) -> bool {
+    archived.name == on_device.name
+        && archived.privilege == on_device.privilege
+        && archived.password == on_device.password
+        && archived.card == on_device.card
+        && archived.group_id.as_deref().unwrap_or("") == on_device.group_id
+}
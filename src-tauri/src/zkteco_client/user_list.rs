@@ -0,0 +1,140 @@
+//! User lists with enrolment state, for enrolment audits
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use log::{info, warn};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// Access level of a user on the terminal (privilege byte, bit 0 = disabled on some firmwares)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivilegeLevel {
+    User,
+    Enroller,
+    Manager,
+    Admin,
+    Unknown,
+}
+
+impl PrivilegeLevel {
+    pub fn from_raw(privilege: u8) -> Self {
+        match privilege & !1 {
+            0 => PrivilegeLevel::User,
+            2 => PrivilegeLevel::Enroller,
+            6 => PrivilegeLevel::Manager,
+            14 => PrivilegeLevel::Admin,
+            _ => PrivilegeLevel::Unknown,
+        }
+    }
+}
+
+/// A user enrolled on a terminal, as shown in enrolment audits. The password
+/// itself is never exposed, only whether one is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceUser {
+    pub uid: u16,
+    pub user_id: String,
+    pub name: String,
+    pub privilege: u8,              // Raw privilege byte
+    pub privilege_level: PrivilegeLevel,
+    pub card: u32,                  // 0 = no card
+    pub group_id: String,
+    pub password_set: bool,
+    pub finger_count: usize,
+    pub has_face: Option<bool>,     // None when the device cannot tell
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceUserList {
+    pub device_info: DeviceInfo,
+    pub device_key: String,
+    pub users: Vec<DeviceUser>,
+}
+
+impl ZKClient {
+    /// Whether a user has a face enrolled, read from the face template slot.
+    /// The template comes back in a data packet, or streamed when it is large
+    /// (pyzk __recieve_chunk); an error reply means the slot is empty. None when
+    /// the device gives no usable answer.
+    async fn has_face(&mut self, uid: u16) -> Option<bool> {
+        let mut request = uid.to_le_bytes().to_vec();
+        request.push(FACE_TEMPLATE_FID);
+        match self.send_command(CMD_GET_USER_TEMPLATE, &request).await {
+            Ok((CMD_DATA | CMD_ACK_OK, data)) if !data.is_empty() => Some(true),
+            Ok((CMD_PREPARE_DATA, data)) if data.len() >= 4 => {
+                // Drain the stream to keep the session in step
+                let size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+                if size == 0 {
+                    return Some(false);
+                }
+                self.read_prepare_data_stream(size).await.ok().map(|(template, _)| !template.is_empty())
+            }
+            Ok((CMD_ACK_ERROR, _)) => Some(false),
+            _ => None,
+        }
+    }
+    
+    /// Users with their enrolment state (fingers from the template table, faces probed per user)
+    async fn list_users(&mut self, faces: Option<&CapacityUsage>) -> Result<Vec<DeviceUser>, ZkError> {
+        let users = self.get_users().await?;
+        let templates = self.get_templates().await?;
+        
+        let mut list = Vec::with_capacity(users.len());
+        for user in &users {
+            let uid = user.uid as u16;
+            let has_face = match faces {
+                // No face storage reported: not a face terminal, or an older firmware
+                None => None,
+                Some(faces) if faces.used == 0 => Some(false),
+                Some(_) => self.has_face(uid).await,
+            };
+            list.push(DeviceUser {
+                uid,
+                user_id: user.user_id.clone(),
+                name: user.name.clone(),
+                privilege: user.privilege,
+                privilege_level: PrivilegeLevel::from_raw(user.privilege),
+                card: user.card,
+                group_id: user.group_id.clone(),
+                password_set: !user.password.is_empty(),
+                finger_count: templates.iter().filter(|t| t.uid == uid && t.valid != 0).count(),
+                has_face,
+            });
+        }
+        Ok(list)
+    }
+}
+
+/// List the users enrolled on a device with their privilege, card and enrolment state
+pub async fn list_device_users(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<DeviceUserList, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    let device_key = client.load_settings(&ip, port, &settings).await;
+    let device_info = client.get_device_info().await;
+    
+    if let Err(e) = client.disable_device().await {
+        warn!("Failed to disable device: {}", e);
+    }
+    let faces = device_info.capacity.as_ref().and_then(|c| c.faces.clone());
+    let users = client.list_users(faces.as_ref()).await;
+    client.disconnect().await?;
+    
+    let users = users?;
+    info!("👥 {} users on {}", users.len(), device_key);
+    Ok(DeviceUserList {
+        device_info,
+        device_key,
+        users,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,56 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[tokio::test]
+async fn lists_users_with_enrolment() {
+    let mut config = SimConfig { face_capacity: 400, ..device(72, 40) };
+    let alice = &mut config.users[0];
+    alice.privilege = 14;
+    alice.password = "1234".to_string();
+    alice.card = 5551234;
+    alice.fingers = vec![0, 6];
+    alice.face = true;
+    config.users[2].fingers = vec![1];
+    let sim = Simulator::start(config).await;
+    
+    let list = list_device_users("127.0.0.1", sim.port, None, settings(&sim.port.to_string())).await.unwrap();
+    assert_eq!(list.device_key, "SIM0000000001");
+    assert_eq!(list.users.len(), 3);
+    
+    let alice = &list.users[0];
+    assert_eq!(alice.privilege_level, PrivilegeLevel::Admin);
+    assert!(alice.password_set);
+    assert_eq!(alice.card, 5551234);
+    assert_eq!(alice.finger_count, 2);
+    assert_eq!(alice.has_face, Some(true));
+    
+    let bala = &list.users[1];
+    assert_eq!(bala.privilege_level, PrivilegeLevel::User);
+    assert!(!bala.password_set);
+    assert_eq!((bala.finger_count, bala.has_face), (0, Some(false)));
+    assert_eq!(list.users[2].finger_count, 1);
+}
+
+#[tokio::test]
+async fn face_templates_are_read_from_data_and_streamed_replies() {
+    for (reply, expected) in [(CMD_DATA, Some(true)), (CMD_PREPARE_DATA, Some(true)), (CMD_ACK_DATA, None)] {
+        let mut config = device(72, 40);
+        config.face_capacity = 100;
+        config.face_reply = reply;
+        config.users[0].face = true;
+        let sim = Simulator::start(config).await;
+        
+        let list = list_device_users("127.0.0.1", sim.port, None, settings(&format!("face-{}", sim.port))).await.unwrap();
+        assert_eq!(list.users[0].has_face, expected, "reply {}", reply);
+        // The stream was drained, so later users are still read in step
+        assert_eq!(list.users[1].has_face, Some(false), "reply {}", reply);
+    }
+}
+
+#[tokio::test]
+async fn face_flag_unknown_without_face_storage() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let list = list_device_users("127.0.0.1", sim.port, None, settings(&sim.port.to_string())).await.unwrap();
+    assert!(list.users.iter().all(|u| u.has_face.is_none()));
+}
@@ -0,0 +1,205 @@
+//! Creating, updating and deleting users
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use log::info;
+use encoding_rs::{Encoding, UTF_8};
+use crate::device_settings::DeviceSettingsStore;
+use crate::zk_error::ZkError;
+use super::*;
+
+/// User to create or update on a device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserInput {
+    pub uid: Option<u16>,       // Internal slot; None = reuse the slot of `user_id` or take the next free one
+    pub user_id: String,        // Badge / employee ID (numeric on 28-byte devices)
+    pub name: String,
+    pub privilege: u8,          // 0 = user, 2 = enroller, 6 = manager, 14 = admin
+    pub password: String,
+    pub card: u32,
+    pub group_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserWriteResult {
+    pub uid: u16,
+    pub user_id: String,
+    pub created: bool,
+}
+
+impl ZKClient {
+    /// Encode a user record in the device's layout (pyzk set_user), with the name in `encoding`
+    pub(super) fn encode_user(record_size: usize, uid: u16, user: &UserInput, encoding: &'static Encoding) -> Result<Vec<u8>, ZkError> {
+        fn pad(field: &str, value: &str, bytes: &[u8], len: usize) -> Result<Vec<u8>, ZkError> {
+            if bytes.len() > len {
+                return Err(ZkError::InvalidArgument(format!("{} '{}' is longer than {} bytes", field, value, len)));
+            }
+            let mut out = bytes.to_vec();
+            out.resize(len, 0);
+            Ok(out)
+        }
+        fn fixed(field: &str, value: &str, len: usize) -> Result<Vec<u8>, ZkError> {
+            pad(field, value, value.as_bytes(), len)
+        }
+        
+        let (name, _, unmappable) = encoding.encode(&user.name);
+        if unmappable {
+            return Err(ZkError::InvalidArgument(format!("Name '{}' cannot be written in the device's {} encoding", user.name, encoding.name())));
+        }
+        
+        let group = user.group_id.clone().unwrap_or_default();
+        let mut buf = Vec::with_capacity(record_size);
+        buf.extend_from_slice(&uid.to_le_bytes());
+        buf.push(user.privilege);
+        
+        if record_size == 28 {
+            // pack('<HB5s8sIxBHI', uid, privilege, password, name, card, group, timezone, user_id)
+            let user_id: u32 = user.user_id.parse()
+                .map_err(|_| ZkError::InvalidArgument(format!("User ID '{}' must be numeric on this device", user.user_id)))?;
+            let group: u8 = if group.is_empty() { 0 } else {
+                group.parse().map_err(|_| ZkError::InvalidArgument(format!("Group '{}' must be a number on this device", group)))?
+            };
+            buf.extend(fixed("Password", &user.password, 5)?);
+            buf.extend(pad("Name", &user.name, &name, 8)?);
+            buf.extend_from_slice(&user.card.to_le_bytes());
+            buf.push(0);
+            buf.push(group);
+            buf.extend_from_slice(&0u16.to_le_bytes());
+            buf.extend_from_slice(&user_id.to_le_bytes());
+        } else {
+            // pack('<HB8s24s4sx7sx24s', uid, privilege, password, name, card, group, user_id)
+            buf.extend(fixed("Password", &user.password, 8)?);
+            buf.extend(pad("Name", &user.name, &name, 24)?);
+            buf.extend_from_slice(&user.card.to_le_bytes());
+            buf.push(0);
+            buf.extend(fixed("Group", &group, 7)?);
+            buf.push(0);
+            buf.extend(fixed("User ID", &user.user_id, 24)?);
+        }
+        
+        Ok(buf)
+    }
+    
+    /// Create or update a user. Reuses the slot of an existing user with the same user_id.
+    async fn set_user(&mut self, user: &UserInput) -> Result<UserWriteResult, ZkError> {
+        let users = self.get_users().await?;
+        let record_size = self.user_packet_size.unwrap_or(28);
+        
+        let badge = users.iter().find(|u| u.user_id == user.user_id);
+        let existing = match user.uid {
+            Some(uid) => {
+                // Writing the badge into a second slot would leave two users with one ID
+                if let Some(other) = badge.filter(|u| u.uid != uid as u32) {
+                    return Err(ZkError::InvalidArgument(format!(
+                        "User ID '{}' already belongs to uid {}, not uid {}", user.user_id, other.uid, uid
+                    )));
+                }
+                let slot = users.iter().find(|u| u.uid == uid as u32);
+                // Writing into the slot of another badge would overwrite that user
+                if let Some(other) = slot.filter(|u| u.user_id != user.user_id) {
+                    return Err(ZkError::InvalidArgument(format!(
+                        "uid {} belongs to user ID '{}', not '{}'", uid, other.user_id, user.user_id
+                    )));
+                }
+                slot
+            }
+            None => badge,
+        };
+        let uid = match (user.uid, existing) {
+            (Some(uid), _) => uid,
+            (None, Some(u)) => u.uid as u16,
+            (None, None) => self.free_uid(&users).await?,
+        };
+        
+        let record = Self::encode_user(record_size, uid, user, self.name_encoding.unwrap_or(UTF_8))?;
+        let (cmd, _) = self.send_command(CMD_USER_WRQ, &record).await?;
+        if cmd != CMD_ACK_OK {
+            return Err(ZkError::reply(&format!("Device rejected user {}", user.user_id), cmd));
+        }
+        self.refresh_data().await?;
+        
+        info!("👤 {} user uid={} badge='{}'", if existing.is_some() { "Updated" } else { "Created" }, uid, user.user_id);
+        Ok(UserWriteResult {
+            uid,
+            user_id: user.user_id.clone(),
+            created: existing.is_none(),
+        })
+    }
+    
+    /// Slot for a new user: after the highest one in use, else the first gap
+    async fn free_uid(&mut self, users: &[User]) -> Result<u16, ZkError> {
+        if let Ok(capacity) = self.read_capacity().await {
+            if capacity.users.capacity > 0 && capacity.users.used >= capacity.users.capacity {
+                return Err(ZkError::DeviceFull(format!(
+                    "Device holds {} of {} users", capacity.users.used, capacity.users.capacity
+                )));
+            }
+        }
+        
+        let taken: HashSet<u16> = users.iter().map(|u| u.uid as u16).collect();
+        taken.iter().max().copied().unwrap_or(0).checked_add(1)
+            .or_else(|| (1..=u16::MAX).find(|uid| !taken.contains(uid)))
+            .ok_or_else(|| ZkError::DeviceFull("Every user slot (uid 1-65535) is taken".to_string()))
+    }
+    
+    /// Delete a user by uid, or look the uid up from user_id
+    pub(super) async fn delete_user(&mut self, uid: Option<u16>, user_id: Option<&str>) -> Result<u16, ZkError> {
+        let uid = match (uid, user_id) {
+            (Some(uid), _) => uid,
+            (None, Some(user_id)) => self.get_users().await?
+                .iter()
+                .find(|u| u.user_id == user_id)
+                .map(|u| u.uid as u16)
+                .ok_or_else(|| ZkError::InvalidArgument(format!("No user with ID '{}' on device", user_id)))?,
+            (None, None) => return Err(ZkError::InvalidArgument("Either uid or user_id is required".to_string())),
+        };
+        
+        let (cmd, _) = self.send_command(CMD_DELETE_USER, &uid.to_le_bytes()).await?;
+        if cmd != CMD_ACK_OK {
+            return Err(ZkError::reply(&format!("Device rejected delete of uid {}", uid), cmd));
+        }
+        self.refresh_data().await?;
+        
+        info!("👤 Deleted user uid={}", uid);
+        Ok(uid)
+    }
+}
+
+/// Create or update a user on the device
+pub async fn set_device_user(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    user: UserInput,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<UserWriteResult, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let result = client.set_user(&user).await;
+    client.disconnect().await?;
+    result
+}
+
+/// Delete a user from the device by uid or badge user_id; returns the uid removed
+pub async fn delete_device_user(
+    ip: &str,
+    port: u16,
+    password: Option<u32>,
+    uid: Option<u16>,
+    user_id: Option<String>,
+    settings: Arc<DeviceSettingsStore>,
+) -> Result<u16, ZkError> {
+    let ip = ip.to_string();
+    
+    let mut client = ZKClient::connect(&ip, port, password.unwrap_or(0)).await?;
+    client.load_settings(&ip, port, &settings).await;
+    let result = client.delete_user(uid, user_id.as_deref()).await;
+    client.disconnect().await?;
+    result
+}
+
+#[cfg(test)]
+mod tests;
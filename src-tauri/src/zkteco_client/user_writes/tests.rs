@@ -0,0 +1,111 @@
+use super::*;
+use crate::zkteco_client::test_support::*;
+use std::sync::Arc;
+use encoding_rs::{GBK, WINDOWS_1252};
+use crate::zk_simulator::{SimConfig, Simulator};
+
+#[test]
+fn encodes_names_in_device_encoding() {
+    let user = UserInput {
+        uid: Some(5),
+        user_id: "1005".to_string(),
+        name: "张伟".to_string(),
+        privilege: 0,
+        password: String::new(),
+        card: 0,
+        group_id: None,
+    };
+    let record = ZKClient::encode_user(72, 5, &user, GBK).unwrap();
+    assert_eq!(&record[11..15], &GBK.encode("张伟").0[..]);
+    
+    let err = ZKClient::encode_user(72, 5, &user, WINDOWS_1252).unwrap_err();
+    assert!(err.to_string().contains("cannot be written"));
+}
+
+#[tokio::test]
+async fn creates_updates_and_deletes_users() {
+    for user_size in [28, 72] {
+        let mut config = device(user_size, 40);
+        config.users[1].fingers = vec![3];
+        let sim = Simulator::start(config).await;
+        let store = settings(&format!("users-{}", sim.port));
+        let set = |user: UserInput| set_device_user("127.0.0.1", sim.port, None, user, Arc::clone(&store));
+        
+        let created = set(user_input(None, "2001", "Dev")).await.unwrap();
+        assert!(created.created);
+        assert_eq!(created.uid, 4);
+        
+        let updated = set(user_input(None, "2001", "Devi")).await.unwrap();
+        assert!(!updated.created);
+        assert_eq!(updated.uid, 4);
+        
+        // Explicit slot of an existing user keeps its enrolment
+        let renamed = set(user_input(Some(2), "1002", "Balan")).await.unwrap();
+        assert!(!renamed.created);
+        let list = list_device_users("127.0.0.1", sim.port, None, Arc::clone(&store)).await.unwrap();
+        let bala = list.users.iter().find(|u| u.uid == 2).unwrap();
+        assert_eq!((bala.name.as_str(), bala.finger_count), ("Balan", 1), "{}-byte users", user_size);
+        assert_eq!(user_ids(&sim, &store).await.len(), 4);
+        
+        let deleted = delete_device_user("127.0.0.1", sim.port, None, None, Some("2001".to_string()), Arc::clone(&store)).await;
+        assert_eq!(deleted.unwrap(), 4);
+        let deleted = delete_device_user("127.0.0.1", sim.port, None, Some(1), None, Arc::clone(&store)).await;
+        assert_eq!(deleted.unwrap(), 1);
+        let remaining: Vec<String> = user_ids(&sim, &store).await.into_iter().map(|(_, id, _)| id).collect();
+        assert_eq!(remaining, vec!["1002", "1003"]);
+        
+        let missing = delete_device_user("127.0.0.1", sim.port, None, None, Some("2001".to_string()), Arc::clone(&store)).await;
+        assert!(missing.is_err());
+    }
+}
+
+#[tokio::test]
+async fn set_user_rejects_badge_held_by_another_slot() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let store = settings(&format!("duplicate-{}", sim.port));
+    
+    let err = set_device_user("127.0.0.1", sim.port, None, user_input(Some(9), "1002", "Bala"), Arc::clone(&store))
+        .await.unwrap_err();
+    assert!(err.to_string().contains("already belongs to uid 2"), "{}", err);
+    let badges: Vec<String> = user_ids(&sim, &store).await.into_iter().map(|(_, id, _)| id).collect();
+    assert_eq!(badges, vec!["1001", "1002", "1003"]);
+}
+
+#[tokio::test]
+async fn set_user_rejects_slot_held_by_another_badge() {
+    let sim = Simulator::start(device(72, 40)).await;
+    let store = settings(&format!("taken-uid-{}", sim.port));
+    
+    let err = set_device_user("127.0.0.1", sim.port, None, user_input(Some(2), "2001", "Dev"), Arc::clone(&store))
+        .await.unwrap_err();
+    assert_eq!(err.code(), "invalid_argument");
+    let users = user_ids(&sim, &store).await;
+    assert!(users.iter().any(|(uid, id, _)| *uid == 2 && id == "1002"), "{:?}", users);
+    
+    // The user's own slot can still be named explicitly
+    let updated = set_device_user("127.0.0.1", sim.port, None, user_input(Some(2), "1002", "Bala K"), Arc::clone(&store))
+        .await.unwrap();
+    assert!(!updated.created);
+}
+
+#[tokio::test]
+async fn new_user_takes_a_free_slot_or_reports_a_full_device() {
+    let mut config = device(72, 40);
+    config.users[2].uid = u16::MAX;
+    let sim = Simulator::start(config.clone()).await;
+    let store = settings(&format!("free-uid-{}", sim.port));
+    
+    let created = set_device_user("127.0.0.1", sim.port, None, user_input(None, "2001", "Dev"), Arc::clone(&store))
+        .await.unwrap();
+    assert_eq!(created.uid, 3);
+    
+    let full = Simulator::start(SimConfig { user_capacity: 3, ..config }).await;
+    let err = set_device_user("127.0.0.1", full.port, None, user_input(None, "2001", "Dev"), Arc::clone(&store))
+        .await.unwrap_err();
+    assert_eq!(err.code(), "device_full");
+    
+    // Updating a user does not need a free slot
+    let updated = set_device_user("127.0.0.1", full.port, None, user_input(None, "1001", "Alicia"), Arc::clone(&store))
+        .await.unwrap();
+    assert_eq!(updated.uid, 1);
+}
@@ -0,0 +1,208 @@
+//! The user table: reading it and decoding user names
+
+use std::collections::HashMap;
+use log::info;
+use encoding_rs::{Encoding, GBK, UTF_8, WINDOWS_1252};
+use crate::zk_error::ZkError;
+use super::*;
+
+#[derive(Debug, Clone)]
+pub(super) struct User {
+    pub(super) uid: u32,
+    pub(super) user_id: String,
+    pub(super) name: String,       // Raw name, may be empty
+    pub(super) privilege: u8,
+    pub(super) password: String,
+    pub(super) card: u32,
+    pub(super) group_id: String,
+}
+
+impl User {
+    fn display_name(&self) -> String {
+        if self.name.is_empty() { format!("User-{}", self.uid) } else { self.name.clone() }
+    }
+    
+    pub(super) fn to_input(&self) -> UserInput {
+        UserInput {
+            uid: Some(self.uid as u16),
+            user_id: self.user_id.clone(),
+            name: self.name.clone(),
+            privilege: self.privilege,
+            password: self.password.clone(),
+            card: self.card,
+            group_id: Some(self.group_id.clone()),
+        }
+    }
+}
+
+/// Decode a fixed-size string field (up to the first NUL, like pyzk)
+pub(super) fn decode_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+/// Decode a NUL-padded user name in the device's code page. A multi-byte
+/// character cut off by the field width is dropped rather than shown as U+FFFD.
+fn decode_name(bytes: &[u8], encoding: &'static Encoding) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let (name, _) = encoding.decode_without_bom_handling(&bytes[..end]);
+    name.trim_end_matches('\u{FFFD}').trim().to_string()
+}
+
+/// Guess the code page of a user table from its raw name fields: UTF-8 if every
+/// name is valid UTF-8 (up to a cut-off last character), GBK if the non-ASCII bytes
+/// only come in pairs that decode cleanly, otherwise Latin-1 (windows-1252)
+fn detect_name_encoding(fields: &[&[u8]]) -> &'static Encoding {
+    // (name, whether it fills the field and so may end inside a character)
+    let names: Vec<(&[u8], bool)> = fields.iter()
+        .map(|f| match f.iter().position(|&b| b == 0) {
+            Some(end) => (&f[..end], false),
+            None => (*f, true),
+        })
+        .filter(|(n, _)| !n.is_ascii())
+        .collect();
+    
+    let utf8 = names.iter().all(|&(n, full)| match std::str::from_utf8(n) {
+        Ok(_) => true,
+        Err(e) => full && e.error_len().is_none(),
+    });
+    if utf8 {
+        return UTF_8;
+    }
+    
+    let gbk = names.iter().all(|&(n, full)| {
+        let body = match n.split(|b| b.is_ascii()).next_back() {
+            Some(run) if full && run.len() % 2 == 1 => &n[..n.len() - 1],
+            _ => n,
+        };
+        body.split(|b| b.is_ascii()).all(|run| run.len() % 2 == 0)
+            && GBK.decode_without_bom_handling_and_without_replacement(body).is_some()
+    });
+    if gbk { GBK } else { WINDOWS_1252 }
+}
+
+impl ZKClient {
+    pub(super) async fn get_users(&mut self) -> Result<Vec<User>, ZkError> {
+        let (data, _) = self.read_with_buffer_pyzk(CMD_USERTEMP_RRQ, FCT_USER).await?;
+        let mut users = Vec::new();
+        
+        if data.len() <= 4 {
+            // No records to measure; only a configured layout is known
+            self.user_packet_size = self.settings.user_size;
+            return Ok(users);
+        }
+        
+        let total_size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let userdata = &data[4..];
+        
+        let record_size = if let Some(size) = self.settings.user_size {
+            size
+        } else if userdata.len() > 0 && total_size > 0 {
+            if userdata.len() >= 72 && userdata.len() % 72 == 0 { 72 }
+            else if userdata.len() >= 28 && userdata.len() % 28 == 0 { 28 }
+            else { 28 }
+        } else { 28 };
+        self.user_packet_size = Some(record_size);
+        
+        let name_range = if record_size == 28 { 8..16 } else { 11..35 };
+        let encoding = self.settings.name_encoding().unwrap_or_else(|| {
+            let names: Vec<&[u8]> = userdata.chunks_exact(record_size).map(|r| &r[name_range.clone()]).collect();
+            detect_name_encoding(&names)
+        });
+        if self.name_encoding != Some(encoding) {
+            info!("User names: {}{}", encoding.name(), if self.settings.name_encoding.is_some() { " (device setting)" } else { "" });
+        }
+        self.name_encoding = Some(encoding);
+        
+        if record_size == 28 {
+            // pyzk: uid, privilege, password, name, card, group_id, timezone, user_id =
+            //       unpack('<HB5s8sIxBhI', ...)
+            let mut offset = 0;
+            while offset + 28 <= userdata.len() {
+                let record = &userdata[offset..offset + 28];
+                let uid = u16::from_le_bytes([record[0], record[1]]) as u32;
+                
+                let mut name = decode_name(&record[8..16], encoding);
+                if name.is_empty() {
+                    // Some firmwares don't follow the pyzk layout - scan the wider window
+                    let window: Vec<u8> = record[2..26].iter().copied().filter(|&b| b != 0).collect();
+                    name = decode_name(&window, encoding);
+                }
+                
+                // Badge number; when unset the uid is what attendance records use
+                let badge = u32::from_le_bytes([record[24], record[25], record[26], record[27]]);
+                let user_id = if badge == 0 { uid.to_string() } else { badge.to_string() };
+                
+                users.push(User {
+                    uid,
+                    user_id,
+                    name,
+                    privilege: record[2],
+                    password: decode_str(&record[3..8]),
+                    card: u32::from_le_bytes([record[16], record[17], record[18], record[19]]),
+                    group_id: record[21].to_string(),
+                });
+                offset += 28;
+            }
+        } else {
+            // 72-byte record format (pyzk)
+            // uid, privilege, password, name, card, group_id, user_id = unpack('<HB8s24sIx7sx24s', ...)
+            let mut offset = 0;
+            while offset + 72 <= userdata.len() {
+                let record = &userdata[offset..offset + 72];
+                let uid = u16::from_le_bytes([record[0], record[1]]) as u32;
+                // Name: bytes 11-35 (24 chars)
+                let name = decode_name(&record[11..35], encoding);
+                // User ID (badge/employee ID): bytes 48-72 (24 chars)
+                let badge_id = decode_str(&record[48..72]);
+                
+                // Use badge_id as user_id (this is what attendance records use)
+                // If badge_id is empty, fall back to uid
+                let user_id = if badge_id.is_empty() { uid.to_string() } else { badge_id };
+                
+                users.push(User {
+                    uid,
+                    user_id,
+                    name,
+                    privilege: record[2],
+                    password: decode_str(&record[3..11]),
+                    card: u32::from_le_bytes([record[35], record[36], record[37], record[38]]),
+                    group_id: decode_str(&record[40..47]),
+                });
+                offset += 72;
+            }
+        }
+        
+        info!("Found {} users", users.len());
+        // Log first few users for debugging
+        for (i, user) in users.iter().take(5).enumerate() {
+            info!("  User {}: uid={}, badge='{}', name='{}'", i+1, user.uid, user.user_id, user.display_name());
+        }
+        Ok(users)
+    }
+    
+    /// Build user lookup (by uid and user_id, with multiple key formats)
+    pub(super) fn build_user_lookup(users: &[User]) -> HashMap<String, String> {
+        let mut user_lookup: HashMap<String, String> = HashMap::new();
+        for user in users {
+            // Add by uid (internal ID)
+            user_lookup.insert(user.uid.to_string(), user.display_name());
+            // Add by user_id string
+            user_lookup.insert(user.user_id.clone(), user.display_name());
+            // Also try parsing user_id as number
+            if let Ok(num) = user.user_id.parse::<u32>() {
+                user_lookup.insert(num.to_string(), user.display_name());
+            }
+            // Extract leading digits from user_id (e.g., "101Emplo" -> "101")
+            let digits: String = user.user_id.chars().take_while(|c| c.is_ascii_digit()).collect();
+            if !digits.is_empty() && digits != user.user_id {
+                user_lookup.insert(digits, user.display_name());
+            }
+        }
+        info!("User lookup: {} keys for {} users", user_lookup.len(), users.len());
+        user_lookup
+    }
+}
+
+#[cfg(test)]
+mod tests;
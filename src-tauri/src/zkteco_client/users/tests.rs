@@ -0,0 +1,22 @@
+use crate::zkteco_client::test_support::*;
+use encoding_rs::{Encoding, GBK, UTF_8, WINDOWS_1252};
+use crate::zk_simulator::Simulator;
+
+#[tokio::test]
+async fn detects_user_name_encoding() {
+    let cases: [(&'static Encoding, usize, &str, &str); 3] = [
+        (GBK, 72, "张伟", "张伟"),
+        (WINDOWS_1252, 72, "José", "José"),
+        // 8-byte field cuts the third Tamil character in half
+        (UTF_8, 28, "முருகன்", "மு"),
+    ];
+    for (encoding, user_size, name, expected) in cases {
+        let mut config = device(user_size, 16);
+        config.users[0].name = name.to_string();
+        config.name_encoding = encoding;
+        let sim = Simulator::start(config).await;
+        
+        let response = fetch(&sim, None).await.unwrap();
+        assert_eq!(response.records[0].user_name, expected, "{}", encoding.name());
+    }
+}